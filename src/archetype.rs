@@ -0,0 +1,116 @@
+use bevy::{
+    asset::{AssetLoader, AssetServer, Assets, Handle, LoadContext, LoadState, LoadedAsset},
+    prelude::{App, Color, Commands, Component, Res},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+/// Stats shared by every monster of one kind, loaded from `assets/monsters/*.monster.ron`
+#[derive(Debug, Deserialize, TypeUuid)]
+#[uuid = "8b1c3f0e-6a2d-4f57-9c1e-2d7b5a4e9f13"]
+pub struct MonsterArchetype {
+    pub name: String,
+    /// sRGB, used as the sprite tint when a sprite is given
+    pub color: (f32, f32, f32),
+    /// Image path relative to the assets folder
+    #[serde(default)]
+    pub sprite: Option<String>,
+    /// Side length of the square body
+    pub size: f32,
+    pub health: f32,
+    /// Units per second
+    pub speed: f32,
+    /// Damage per second dealt to the player while touching them
+    pub contact_damage: f32,
+    pub mass: f32,
+    pub xp: u32,
+    /// Relative chance of being picked for a spawn among the unlocked archetypes
+    pub spawn_weight: f32,
+    /// Seconds into the run before this archetype starts spawning
+    #[serde(default)]
+    pub spawn_after: f32,
+}
+
+impl MonsterArchetype {
+    pub fn color(&self) -> Color {
+        let (r, g, b) = self.color;
+        Color::rgb(r, g, b)
+    }
+}
+
+#[derive(Component)]
+pub struct MonsterKind(pub Handle<MonsterArchetype>);
+
+impl MonsterKind {
+    /// The monster's archetype. Runs only start once every archetype is loaded, see
+    /// `wait_for_archetypes` and `main_menu_input`, and nothing unloads them afterwards.
+    pub fn archetype<'a>(&self, archetypes: &'a Assets<MonsterArchetype>) -> &'a MonsterArchetype {
+        archetypes
+            .get(&self.0)
+            .expect("archetype should be loaded before a run starts")
+    }
+}
+
+/// Handles keeping every archetype in `assets/monsters` loaded
+#[derive(Default)]
+pub struct MonsterArchetypes(pub Vec<Handle<MonsterArchetype>>);
+
+impl MonsterArchetypes {
+    pub fn load_state(&self, asset_server: &AssetServer) -> LoadState {
+        asset_server.get_group_load_state(self.0.iter().map(|handle| handle.id))
+    }
+
+    pub fn loaded(&self, asset_server: &AssetServer) -> bool {
+        self.load_state(asset_server) == LoadState::Loaded
+    }
+}
+
+/// Updates `app` until every archetype is loaded, for apps that can't wait on the main menu.
+/// Panics if any of them fail to load.
+pub fn wait_for_archetypes(app: &mut App) {
+    loop {
+        app.update();
+
+        // The archetypes' handles only show up once the startup systems ran
+        let asset_server = app.world.get_resource::<AssetServer>().unwrap();
+        let archetypes = app.world.get_resource::<MonsterArchetypes>();
+        match archetypes.map(|archetypes| archetypes.load_state(asset_server)) {
+            Some(LoadState::Loaded) => break,
+            Some(LoadState::Failed) => panic!("monster archetypes failed to load"),
+            _ => std::thread::yield_now(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct MonsterArchetypeLoader;
+
+impl AssetLoader for MonsterArchetypeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let archetype = ron::de::from_bytes::<MonsterArchetype>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(archetype));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["monster.ron"]
+    }
+}
+
+pub fn load_monster_archetypes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = asset_server
+        .load_folder("monsters")
+        .expect("monster archetype folder should be readable")
+        .into_iter()
+        .map(|handle| handle.typed())
+        .collect();
+
+    commands.insert_resource(MonsterArchetypes(handles));
+}
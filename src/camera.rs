@@ -0,0 +1,140 @@
+use bevy::{
+    core::Time,
+    input::Input,
+    math::Vec2,
+    prelude::{
+        Component, GamepadButton, GamepadButtonType, Gamepads, KeyCode, OrthographicProjection,
+        Query, Res, Transform, With, Without,
+    },
+    window::Windows,
+};
+
+use crate::{ui::menu_pressed, view_half_extents, MainCamera, Player};
+
+/// Moves the camera it is attached to along with the player
+#[derive(Component)]
+pub struct CameraFollow {
+    /// How quickly the camera catches up, as the fraction of the remaining distance left after
+    /// one second is `e^-smoothing`. Zero snaps straight to the target.
+    pub smoothing: f32,
+    /// Half the size of the box around the view's center the player can move in without the
+    /// camera following
+    pub dead_zone: Vec2,
+    /// Lower and upper corners of the area the view is kept inside of
+    pub bounds: Option<(Vec2, Vec2)>,
+    /// Projection scales cycled through by zooming, larger showing more of the world
+    pub zoom_levels: Vec<f32>,
+    pub zoom_level: usize,
+}
+
+impl Default for CameraFollow {
+    fn default() -> Self {
+        Self {
+            smoothing: 6.0,
+            dead_zone: Vec2::new(40.0, 30.0),
+            bounds: None,
+            zoom_levels: vec![0.75, 1.0, 1.5, 2.0],
+            zoom_level: 1,
+        }
+    }
+}
+
+impl CameraFollow {
+    fn zoom(&self) -> f32 {
+        self.zoom_levels[self.zoom_level]
+    }
+}
+
+pub fn follow_player(
+    time: Res<Time>,
+    windows: Res<Windows>,
+    player_query: Query<&Transform, With<Player>>,
+    mut camera_query: Query<
+        (&CameraFollow, &OrthographicProjection, &mut Transform),
+        (With<MainCamera>, Without<Player>),
+    >,
+) {
+    let player = match player_query.get_single() {
+        Ok(player) => player.translation.truncate(),
+        Err(_) => return,
+    };
+
+    for (follow, projection, mut transform) in camera_query.iter_mut() {
+        let center = transform.translation.truncate();
+
+        // Only follow far enough to bring the player back to the edge of the dead zone
+        let offset = player - center;
+        let excess = offset.abs() - follow.dead_zone;
+        let target = center + offset.signum() * excess.max(Vec2::ZERO);
+
+        let blend = if follow.smoothing > 0.0 {
+            1.0 - (-follow.smoothing * time.delta_seconds()).exp()
+        } else {
+            1.0
+        };
+        let mut center = center.lerp(target, blend);
+
+        if let Some((min, max)) = follow.bounds {
+            center = clamp_to_bounds(center, min, max, view_half_extents(&windows, projection));
+        }
+
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
+
+/// Keeps the view inside of the bounds, centering it on them when it is the larger of the two
+fn clamp_to_bounds(center: Vec2, min: Vec2, max: Vec2, half_extents: Option<Vec2>) -> Vec2 {
+    let half_extents = half_extents.unwrap_or(Vec2::ZERO);
+    let low = min + half_extents;
+    let high = max - half_extents;
+    let middle = (min + max) / 2.0;
+
+    Vec2::new(
+        if low.x <= high.x {
+            center.x.clamp(low.x, high.x)
+        } else {
+            middle.x
+        },
+        if low.y <= high.y {
+            center.y.clamp(low.y, high.y)
+        } else {
+            middle.y
+        },
+    )
+}
+
+/// Steps through the zoom levels with the minus and equals keys or a gamepad's shoulder buttons
+pub fn zoom_camera(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut camera_query: Query<(&mut CameraFollow, &mut OrthographicProjection), With<MainCamera>>,
+) {
+    let pressed = menu_pressed(&keyboard_input, &gamepads, &gamepad_input);
+    let zoom_out = pressed(KeyCode::Minus, GamepadButtonType::LeftTrigger);
+    let zoom_in = pressed(KeyCode::Equals, GamepadButtonType::RightTrigger);
+
+    for (mut follow, mut projection) in camera_query.iter_mut() {
+        if zoom_out && follow.zoom_level + 1 < follow.zoom_levels.len() {
+            follow.zoom_level += 1;
+        }
+        if zoom_in && follow.zoom_level > 0 {
+            follow.zoom_level -= 1;
+        }
+
+        if projection.scale != follow.zoom() {
+            projection.scale = follow.zoom();
+        }
+    }
+}
+
+/// Puts the camera back over the player's starting point for a new run
+pub fn reset_camera(
+    mut camera_query: Query<&mut Transform, (With<CameraFollow>, Without<Player>)>,
+) {
+    for mut transform in camera_query.iter_mut() {
+        transform.translation.x = 0.0;
+        transform.translation.y = 0.0;
+    }
+}
@@ -0,0 +1,282 @@
+use bevy::{
+    app::Plugin,
+    asset::Assets,
+    diagnostic::{Diagnostic, DiagnosticId, Diagnostics},
+    math::Vec2,
+    prelude::{
+        App, Commands, Entity, EventReader, EventWriter, ParallelSystemDescriptorCoercion, Query,
+        Res, ResMut, Transform, With, Without,
+    },
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    physics::{IntoEntity, IntoHandle, RapierConfiguration},
+    prelude::{IntersectionEvent, NarrowPhase, RigidBodyPositionComponent},
+};
+
+use crate::{
+    archetype::{MonsterArchetype, MonsterKind},
+    input::TickInput,
+    spatial::SpatialIndex,
+    weapon::fire_weapons,
+    AddGameplaySystem, CombatSystem, Damage, DamageCause, Died, FixedTime, Health, Monster,
+    Obstacle, Player, Projectile, TickPhase,
+};
+
+/// Distance from the player within which monsters are checked for contact, covering the player
+/// and the largest monsters with room to spare for movement since the index was built
+const CONTACT_SEARCH_RADIUS: f32 = 150.0;
+
+/// Extra distance beyond the edges of the view around the player before projectiles are cleaned
+/// up. Generous, since the camera trails behind the player.
+const PROJECTILE_CULL_MARGIN: f32 = 150.0;
+
+const LIVE_PROJECTILES: DiagnosticId =
+    DiagnosticId::from_u128(0x5f3a_2c1e_9b4d_4e7a_8c6f_1d2e_3b4a_5c6d);
+
+/// Weapons firing projectiles, and projectiles and monster contact dealing damage until something
+/// dies
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_gameplay_event::<Damage>()
+            .add_gameplay_event::<Died>()
+            .add_startup_system(setup_diagnostics)
+            .add_gameplay_system(TickPhase::Ai, fire_weapons)
+            .add_gameplay_system(TickPhase::Movement, projectile_movement)
+            .add_gameplay_system(
+                TickPhase::Combat,
+                projectile_hits.label(CombatSystem::DealDamage),
+            )
+            .add_gameplay_system(
+                TickPhase::Combat,
+                player_damage.label(CombatSystem::DealDamage),
+            )
+            .add_gameplay_system(
+                TickPhase::Combat,
+                apply_damage
+                    .label(CombatSystem::ApplyDamage)
+                    .after(CombatSystem::DealDamage),
+            )
+            .add_gameplay_system(TickPhase::Cleanup, projectile_cleanup);
+    }
+}
+
+fn setup_diagnostics(mut diagnostics: ResMut<Diagnostics>) {
+    diagnostics.add(Diagnostic::new(LIVE_PROJECTILES, "live_projectiles", 20));
+}
+
+fn projectile_movement(
+    fixed_time: Res<FixedTime>,
+    rapier_config: Res<RapierConfiguration>,
+    mut projectile_query: Query<(&mut RigidBodyPositionComponent, &mut Projectile)>,
+) {
+    for (mut rb_pos, mut projectile) in projectile_query.iter_mut() {
+        let step = projectile.direction * projectile.speed * fixed_time.delta_seconds();
+        projectile.travelled += step.length();
+
+        let step = step / rapier_config.scale;
+        rb_pos.next_position.translation.vector += Vector2::new(step.x, step.y);
+    }
+}
+
+fn projectile_cleanup(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    tick_input: Res<TickInput>,
+    mut diagnostics: ResMut<Diagnostics>,
+    player_query: Query<&Transform, With<Player>>,
+    mut projectile_query: Query<(Entity, &Transform, &mut Projectile), Without<Player>>,
+) {
+    // Culled around the player rather than the camera, which isn't part of the simulation. Without
+    // a window there is no view to cull against, only range and lifetime apply.
+    let view = tick_input
+        .view_half_extents
+        .zip(player_query.get_single().ok())
+        .map(|(half_extents, player)| {
+            (
+                player.translation.truncate(),
+                half_extents + Vec2::splat(PROJECTILE_CULL_MARGIN),
+            )
+        });
+
+    let mut live = 0;
+    for (entity, transform, mut projectile) in projectile_query.iter_mut() {
+        // Spent projectiles are already being despawned by `projectile_hits`
+        if projectile.lives == 0 {
+            continue;
+        }
+
+        let expired = projectile.lifetime.tick(fixed_time.step).finished()
+            || projectile.travelled >= projectile.range;
+
+        let off_screen = view.map_or(false, |(center, half_extents)| {
+            let offset = (transform.translation.truncate() - center).abs();
+            offset.x > half_extents.x || offset.y > half_extents.y
+        });
+
+        if expired || off_screen {
+            commands.entity(entity).despawn();
+        } else {
+            live += 1;
+        }
+    }
+
+    diagnostics.add_measurement(LIVE_PROJECTILES, live as f64);
+}
+
+fn projectile_hits(
+    mut commands: Commands,
+    monster_index: Res<SpatialIndex<Monster>>,
+    mut intersection_events: EventReader<IntersectionEvent>,
+    mut damage_events: EventWriter<Damage>,
+    mut projectiles: Query<(&mut Projectile, &Transform)>,
+    monsters: Query<&Health, With<Monster>>,
+    obstacles: Query<Entity, With<Obstacle>>,
+) {
+    for event in intersection_events.iter() {
+        if !event.intersecting {
+            continue;
+        }
+
+        // Sensor events don't guarantee an order between the two colliders
+        let (a, b) = (event.collider1.entity(), event.collider2.entity());
+        let (projectile_entity, other) = if projectiles.get(a).is_ok() {
+            (a, b)
+        } else if projectiles.get(b).is_ok() {
+            (b, a)
+        } else {
+            continue;
+        };
+
+        let (mut projectile, transform) = projectiles.get_mut(projectile_entity).unwrap();
+
+        // A spent projectile can still report intersections until it is despawned
+        if projectile.lives == 0 {
+            continue;
+        }
+
+        if obstacles.get(other).is_ok() {
+            // Obstacles stop projectiles outright, regardless of pierce
+            projectile.lives = 0;
+            commands.entity(projectile_entity).despawn();
+            continue;
+        }
+
+        // Monsters that died earlier this tick linger until `monster_death` despawns them
+        match monsters.get(other) {
+            Ok(health) if health.0 > 0.0 && !projectile.hits.contains(&other) => {}
+            _ => continue,
+        }
+
+        damage_events.send(Damage {
+            target: other,
+            amount: projectile.damage,
+            cause: DamageCause::Projectile(projectile.weapon),
+        });
+
+        if projectile.blast_radius > 0.0 {
+            let blast = monster_index
+                .within_radius(transform.translation.truncate(), projectile.blast_radius)
+                .filter(|(monster, _)| *monster != other);
+            for (monster, _) in blast {
+                damage_events.send(Damage {
+                    target: monster,
+                    amount: projectile.damage,
+                    cause: DamageCause::Projectile(projectile.weapon),
+                });
+            }
+        }
+
+        projectile.hits.push(other);
+        projectile.lives -= 1;
+        if projectile.lives == 0 {
+            commands.entity(projectile_entity).despawn();
+        }
+    }
+}
+
+fn player_damage(
+    fixed_time: Res<FixedTime>,
+    narrow_phase: Res<NarrowPhase>,
+    archetypes: Res<Assets<MonsterArchetype>>,
+    monster_index: Res<SpatialIndex<Monster>>,
+    mut damage_events: EventWriter<Damage>,
+    monsters: Query<&MonsterKind, With<Monster>>,
+    player: Query<(Entity, &Transform), With<Player>>,
+) {
+    for (player, transform) in player.iter() {
+        let nearby =
+            monster_index.within_radius(transform.translation.truncate(), CONTACT_SEARCH_RADIUS);
+
+        for (monster, _) in nearby {
+            // Monsters indexed this tick may have been despawned since
+            let archetype = match monsters.get(monster) {
+                Ok(kind) => kind.archetype(&archetypes),
+                Err(_) => continue,
+            };
+
+            if let Some(contact) = narrow_phase.contact_pair(player.handle(), monster.handle()) {
+                if contact.has_any_active_contact {
+                    damage_events.send(Damage {
+                        target: player,
+                        amount: archetype.contact_damage * fixed_time.delta_seconds(),
+                        cause: DamageCause::Contact,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn apply_damage(
+    mut damage_events: EventReader<Damage>,
+    mut died_events: EventWriter<Died>,
+    mut health_query: Query<(&mut Health, &Transform)>,
+) {
+    for damage in damage_events.iter() {
+        if let Ok((mut health, transform)) = health_query.get_mut(damage.target) {
+            // Already dead entities don't die twice
+            if health.0 <= 0.0 {
+                continue;
+            }
+
+            health.0 -= damage.amount;
+            if health.0 <= 0.0 {
+                died_events.send(Died {
+                    entity: damage.target,
+                    translation: transform.translation,
+                    cause: damage.cause,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use crate::{testing::TestApp, Health, TICKS_PER_SECOND};
+
+    #[test]
+    fn monster_contact_damages_player_over_time() {
+        let mut app = TestApp::new();
+        let player = app.spawn_player(Vec2::ZERO);
+        // Zombies are as wide as the player, so this one starts out pressed against them
+        app.spawn_monster("Zombie", Vec2::new(49.0, 0.0));
+
+        app.tick(TICKS_PER_SECOND);
+
+        // Contact may only register from the second tick on
+        let lost = 100.0 - app.get::<Health>(player).unwrap().0;
+        let contact_damage = app.archetype("Zombie").contact_damage;
+        assert!(
+            (lost - contact_damage).abs() <= contact_damage / TICKS_PER_SECOND as f32 + 0.001,
+            "player lost {} health in a second, expected {}",
+            lost,
+            contact_damage
+        );
+    }
+}
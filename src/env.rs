@@ -0,0 +1,269 @@
+use bevy::{
+    core::DefaultTaskPoolOptions,
+    math::Vec2,
+    prelude::{App, Mut, Res, ResMut, State, Transform, With},
+};
+
+use crate::{
+    archetype::wait_for_archetypes,
+    headless::Bot,
+    input::{Movement, TickInput},
+    progression::ExperienceGem,
+    spatial::CellGrid,
+    AddGameplaySystem, AppState, FixedTime, HeadlessPlugins, Health, KillCount, MaxHealth, Monster,
+    Obstacle, Player, RunSettings, RunTime, TickPhase, VampsPlugin, TICKS_PER_SECOND,
+};
+
+/// Cells on a side of the observation grids
+pub const GRID_SIZE: usize = 11;
+
+/// Side length of an observation grid cell
+pub const GRID_CELL_SIZE: f32 = 64.0;
+
+/// Length of an `Observation` flattened into a `Vec`: health, then the monster, gem and obstacle
+/// grids
+pub const OBSERVATION_LEN: usize = 1 + 3 * GRID_SIZE * GRID_SIZE;
+
+/// Cells of the observation grids, with the player in the middle of the center cell
+const OBSERVATION_GRID: CellGrid = CellGrid {
+    cell_size: Vec2::new(GRID_CELL_SIZE, GRID_CELL_SIZE),
+    origin: Vec2::new(GRID_SIZE as f32 / 2.0, GRID_SIZE as f32 / 2.0),
+    columns: GRID_SIZE as i32,
+    rows: GRID_SIZE as i32,
+};
+
+/// Size of the default window. The simulation pretends to show this much of the world, so
+/// monsters spawn and projectiles are culled at the same distances as when playing.
+const VIEW_SIZE: (f32, f32) = (1280.0, 720.0);
+
+/// Reward for every tick survived, adding up to 1 per second
+const SURVIVAL_REWARD: f32 = 1.0 / TICKS_PER_SECOND as f32;
+
+/// Reward for every monster killed
+const KILL_REWARD: f32 = 0.1;
+
+/// Penalty for losing health, per whole health bar lost
+const HEALTH_PENALTY: f32 = 1.0;
+
+/// What an agent sees of the world after a tick. The grids are `GRID_SIZE` cells on a side,
+/// centered on the player and stored row by row from the top left.
+#[derive(Clone, Debug)]
+pub struct Observation {
+    /// Player health as a fraction of their maximum health
+    pub health: f32,
+    /// Number of monsters in every cell
+    pub monsters: Vec<f32>,
+    /// Number of experience gems in every cell
+    pub gems: Vec<f32>,
+    /// 1 for cells an obstacle overlaps, 0 for the others
+    pub obstacles: Vec<f32>,
+}
+
+impl Observation {
+    fn empty(health: f32) -> Self {
+        let cells = GRID_SIZE * GRID_SIZE;
+        Self {
+            health,
+            monsters: vec![0.0; cells],
+            gems: vec![0.0; cells],
+            obstacles: vec![0.0; cells],
+        }
+    }
+
+    /// Offset of the center of grid cell `index` from the player
+    pub fn cell_offset(index: usize) -> Vec2 {
+        let half = (GRID_SIZE / 2) as f32;
+        let (row, column) = ((index / GRID_SIZE) as f32, (index % GRID_SIZE) as f32);
+        Vec2::new(column - half, half - row) * GRID_CELL_SIZE
+    }
+}
+
+/// Flattens the observation into `OBSERVATION_LEN` values, in the order the fields are declared
+impl From<&Observation> for Vec<f32> {
+    fn from(observation: &Observation) -> Self {
+        let mut values = Vec::with_capacity(OBSERVATION_LEN);
+        values.push(observation.health);
+        values.extend(&observation.monsters);
+        values.extend(&observation.gems);
+        values.extend(&observation.obstacles);
+        values
+    }
+}
+
+/// Movement the agent picked for the next tick
+#[derive(Default)]
+struct Action(Movement);
+
+/// The gameplay simulation as a reinforcement learning environment, advancing one tick per
+/// `step`. Runs without a window and as fast as it can.
+///
+/// Every environment owns a separate `App` running on a single thread, so many can run side by
+/// side. The `App` can't be sent across threads, so create each environment on the thread that
+/// steps it.
+pub struct VampsEnv {
+    app: App,
+    /// Kills and health after the last step, to reward the difference
+    kills: usize,
+    health: f32,
+}
+
+impl VampsEnv {
+    /// Builds the simulation and waits for its assets to load, panicking if they can't be
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.insert_resource(DefaultTaskPoolOptions::with_num_threads(1))
+            .add_plugins(HeadlessPlugins)
+            .add_plugin(VampsPlugin)
+            // Level ups are left to the bot, the agent only moves
+            .insert_resource(Bot)
+            .init_resource::<Action>()
+            .add_gameplay_system(TickPhase::Input, apply_action);
+        wait_for_archetypes(&mut app);
+
+        Self {
+            app,
+            kills: 0,
+            health: 0.0,
+        }
+    }
+
+    /// Starts a new run with the given seed, throwing away the current one
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.app
+            .world
+            .get_resource_mut::<RunSettings>()
+            .unwrap()
+            .seed = Some(seed);
+
+        // Entering `Playing` again is what starts a new run
+        if *self.state().current() != AppState::MainMenu {
+            self.state_mut().replace(AppState::MainMenu).unwrap();
+            self.app.update();
+        }
+        self.state_mut().set(AppState::Playing).unwrap();
+        self.app.update();
+
+        self.kills = 0;
+        self.health = self.player_health().0;
+        self.observe()
+    }
+
+    /// Moves in the direction of `action` for one tick. Returns what the world looks like after
+    /// it, the reward for it and whether the run is over. A run has to be started with `reset`
+    /// first.
+    pub fn step(&mut self, action: Movement) -> (Observation, f32, bool) {
+        if self.is_done() {
+            return (self.observe(), 0.0, true);
+        }
+
+        self.app.world.get_resource_mut::<Action>().unwrap().0 = action;
+
+        // Level ups take a few frames without ticks to get through
+        let start = self.app.world.get_resource::<RunTime>().unwrap().0;
+        while self.app.world.get_resource::<RunTime>().unwrap().0 == start {
+            if *self.state().current() == AppState::Playing {
+                let mut fixed_time = self.app.world.get_resource_mut::<FixedTime>().unwrap();
+                fixed_time.accumulator = fixed_time.step;
+            }
+            self.app.update();
+        }
+
+        let kills = self.app.world.get_resource::<KillCount>().unwrap().0;
+        let (health, max_health) = self.player_health();
+        let health_lost = (self.health - health).max(0.0) / max_health;
+        let reward = SURVIVAL_REWARD + (kills - self.kills) as f32 * KILL_REWARD
+            - health_lost * HEALTH_PENALTY;
+
+        self.kills = kills;
+        self.health = health;
+        (self.observe(), reward, self.is_done())
+    }
+
+    /// Whether the player died
+    pub fn is_done(&self) -> bool {
+        self.health <= 0.0
+    }
+
+    /// The app running the simulation, for adding systems to it or looking at anything the
+    /// observations leave out
+    pub fn app_mut(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    fn state(&self) -> &State<AppState> {
+        self.app.world.get_resource::<State<AppState>>().unwrap()
+    }
+
+    fn state_mut(&mut self) -> Mut<State<AppState>> {
+        self.app
+            .world
+            .get_resource_mut::<State<AppState>>()
+            .unwrap()
+    }
+
+    /// The player's health and maximum health. The player stays around after dying, until the
+    /// next run starts.
+    fn player_health(&mut self) -> (f32, f32) {
+        let world = &mut self.app.world;
+        let (health, max_health) = world
+            .query_filtered::<(&Health, &MaxHealth), With<Player>>()
+            .iter(world)
+            .next()
+            .expect("player should exist once a run started");
+        (health.0, max_health.0)
+    }
+
+    fn observe(&mut self) -> Observation {
+        let (health, max_health) = self.player_health();
+        let mut observation = Observation::empty(health / max_health);
+
+        let world = &mut self.app.world;
+        let player = world
+            .query_filtered::<&Transform, With<Player>>()
+            .iter(world)
+            .next()
+            .expect("player should exist once a run started")
+            .translation
+            .truncate();
+
+        for transform in world
+            .query_filtered::<&Transform, With<Monster>>()
+            .iter(world)
+        {
+            if let Some(cell) = OBSERVATION_GRID.cell(transform.translation.truncate() - player) {
+                observation.monsters[cell] += 1.0;
+            }
+        }
+
+        for transform in world
+            .query_filtered::<&Transform, With<ExperienceGem>>()
+            .iter(world)
+        {
+            if let Some(cell) = OBSERVATION_GRID.cell(transform.translation.truncate() - player) {
+                observation.gems[cell] += 1.0;
+            }
+        }
+
+        for transform in world
+            .query_filtered::<&Transform, With<Obstacle>>()
+            .iter(world)
+        {
+            for cell in OBSERVATION_GRID.obstacle_cells(player, transform) {
+                observation.obstacles[cell] = 1.0;
+            }
+        }
+
+        observation
+    }
+}
+
+impl Default for VampsEnv {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn apply_action(action: Res<Action>, mut tick_input: ResMut<TickInput>) {
+    tick_input.movement = action.0;
+    tick_input.view_half_extents = Some(Vec2::new(VIEW_SIZE.0, VIEW_SIZE.1) / 2.0);
+}
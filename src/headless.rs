@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use bevy::{
+    math::Vec2,
+    prelude::{EventReader, ResMut, With},
+};
+use ordered_float::OrderedFloat;
+use serde::Serialize;
+
+use crate::{
+    env::{Observation, VampsEnv},
+    input::Movement,
+    progression::Level,
+    upgrade::Upgrade,
+    AddGameplaySystem, Damage, DamageCause, Died, KillCount, Player, RunTime, TickPhase,
+    TICKS_PER_SECOND,
+};
+
+/// How long a headless run lasts at most when `--minutes` isn't given
+pub const DEFAULT_MINUTES: f32 = 30.0;
+
+/// Monsters closer than this to the bot are run away from
+const THREAT_RADIUS: f32 = 250.0;
+
+/// Plays headless runs. It isn't meant to play well, only to play the same way every time, so
+/// balance changes show up in the summary rather than in how the player happened to move.
+pub struct Bot;
+
+impl Bot {
+    /// Steers away from nearby monsters, or towards the closest gem when none are near
+    pub fn act(&self, observation: &Observation) -> Movement {
+        // The closest monsters weigh the most. Monsters in the player's own cell give no
+        // direction to run in.
+        let mut heading = observation
+            .monsters
+            .iter()
+            .enumerate()
+            .map(|(cell, count)| (Observation::cell_offset(cell), *count))
+            .filter(|(offset, count)| {
+                *count > 0.0 && *offset != Vec2::ZERO && offset.length() <= THREAT_RADIUS
+            })
+            .fold(Vec2::ZERO, |sum, (offset, count)| {
+                sum - offset / offset.length_squared() * count
+            });
+
+        if heading == Vec2::ZERO {
+            let closest_gem = observation
+                .gems
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| **count > 0.0)
+                .map(|(cell, _)| Observation::cell_offset(cell))
+                .min_by_key(|offset| OrderedFloat(offset.length_squared()));
+            if let Some(offset) = closest_gem {
+                heading = offset;
+            }
+        }
+
+        Movement::towards(heading)
+    }
+
+    /// Index into `options` of the upgrade to take: new weapons first, then weapon levels
+    pub fn pick_upgrade(&self, options: &[Upgrade]) -> usize {
+        let priority = |upgrade: &Upgrade| match upgrade {
+            Upgrade::NewWeapon(_) => 0,
+            Upgrade::WeaponLevel(_) => 1,
+            Upgrade::MaxHealth => 2,
+            Upgrade::MoveSpeed | Upgrade::PickupRadius => 3,
+        };
+
+        options
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, upgrade)| priority(upgrade))
+            .map_or(0, |(index, _)| index)
+    }
+}
+
+/// Damage dealt and monsters killed by every weapon over the run, keyed by weapon name. Damage
+/// includes overkill.
+#[derive(Default)]
+struct WeaponStats {
+    damage: BTreeMap<&'static str, f32>,
+    kills: BTreeMap<&'static str, usize>,
+}
+
+/// How a headless run went, printed as JSON by `--headless`
+#[derive(Serialize)]
+pub struct Summary {
+    pub seed: u64,
+    /// Seconds of simulated time survived
+    pub survival_time: f32,
+    pub died: bool,
+    pub level: u32,
+    pub kills: usize,
+    pub damage_per_weapon: BTreeMap<&'static str, f32>,
+    pub kills_per_weapon: BTreeMap<&'static str, usize>,
+}
+
+/// Plays a single run with `Bot` until the player dies or `minutes` of simulated time passed
+pub fn simulate(seed: u64, minutes: f32) -> Summary {
+    let mut env = VampsEnv::new();
+    env.app_mut()
+        .init_resource::<WeaponStats>()
+        .add_gameplay_system(TickPhase::Cleanup, tally_weapon_stats);
+
+    let bot = Bot;
+    let mut observation = env.reset(seed);
+    let ticks = (minutes * 60.0 * TICKS_PER_SECOND as f32) as u64;
+    for _ in 0..ticks {
+        let (next, _, done) = env.step(bot.act(&observation));
+        if done {
+            break;
+        }
+        observation = next;
+    }
+
+    let died = env.is_done();
+    let world = &mut env.app_mut().world;
+    let level = world
+        .query_filtered::<&Level, With<Player>>()
+        .iter(world)
+        .next()
+        .expect("player should outlast the run")
+        .0;
+    let stats = world.remove_resource::<WeaponStats>().unwrap();
+    Summary {
+        seed,
+        survival_time: world.get_resource::<RunTime>().unwrap().0.as_secs_f32(),
+        died,
+        level,
+        kills: world.get_resource::<KillCount>().unwrap().0,
+        damage_per_weapon: stats.damage,
+        kills_per_weapon: stats.kills,
+    }
+}
+
+fn tally_weapon_stats(
+    mut damage_events: EventReader<Damage>,
+    mut died_events: EventReader<Died>,
+    mut stats: ResMut<WeaponStats>,
+) {
+    for damage in damage_events.iter() {
+        if let DamageCause::Projectile(weapon) = damage.cause {
+            *stats.damage.entry(weapon.name()).or_default() += damage.amount;
+        }
+    }
+
+    for died in died_events.iter() {
+        if let DamageCause::Projectile(weapon) = died.cause {
+            *stats.kills.entry(weapon.name()).or_default() += 1;
+        }
+    }
+}
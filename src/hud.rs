@@ -0,0 +1,305 @@
+use bevy::{
+    asset::Handle,
+    prelude::{
+        Added, AlignItems, BuildChildren, Changed, ChildBuilder, Color, Commands, Component,
+        DespawnRecursiveExt, Entity, JustifyContent, NodeBundle, Or, PositionType, Query, Rect,
+        Res, Size, Style, Text, TextBundle, TextStyle, Val, With, Without,
+    },
+    text::Font,
+};
+
+use crate::{
+    progression::{Experience, Level, XpCurve},
+    ui::{text_style, COLUMN},
+    weapon::Weapon,
+    Health, KillCount, MaxHealth, Player, RunEntity, RunTime, UiFont,
+};
+
+const BAR_WIDTH: f32 = 240.0;
+const BAR_HEIGHT: f32 = 20.0;
+const WEAPON_ICON_SIZE: f32 = 40.0;
+
+#[derive(Component)]
+pub struct HealthBar;
+
+#[derive(Component)]
+pub struct HealthText;
+
+#[derive(Component)]
+pub struct XpBar;
+
+#[derive(Component)]
+pub struct LevelText;
+
+#[derive(Component)]
+pub struct RunTimeText;
+
+#[derive(Component)]
+pub struct KillCountText;
+
+/// Row holding an icon per weapon the player carries
+#[derive(Component)]
+pub struct WeaponIcons;
+
+#[derive(Component)]
+pub struct WeaponIcon;
+
+pub fn spawn_hud(mut commands: Commands, font: Res<UiFont>) {
+    let text_style = text_style(&font, 20.0);
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::SpaceBetween,
+                padding: Rect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .insert(RunEntity)
+        .with_children(|hud| {
+            // Bars and weapons on the left
+            hud.spawn_bundle(NodeBundle {
+                style: Style {
+                    flex_direction: COLUMN,
+                    ..Default::default()
+                },
+                color: Color::NONE.into(),
+                ..Default::default()
+            })
+            .with_children(|column| {
+                spawn_bar(
+                    column,
+                    &text_style,
+                    Color::rgb(0.8, 0.1, 0.1),
+                    HealthBar,
+                    HealthText,
+                );
+                spawn_bar(
+                    column,
+                    &text_style,
+                    Color::rgb(0.2, 0.6, 1.0),
+                    XpBar,
+                    LevelText,
+                );
+                column
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            margin: Rect::all(Val::Px(4.0)),
+                            ..Default::default()
+                        },
+                        color: Color::NONE.into(),
+                        ..Default::default()
+                    })
+                    .insert(WeaponIcons);
+            });
+
+            spawn_text(hud, &text_style, RunTimeText);
+            spawn_text(hud, &text_style, KillCountText);
+        });
+}
+
+/// A bar filled to some fraction of its width, with a label on top
+fn spawn_bar(
+    parent: &mut ChildBuilder,
+    text_style: &TextStyle,
+    color: Color,
+    bar: impl Component,
+    label: impl Component,
+) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                margin: Rect::all(Val::Px(4.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..Default::default()
+            },
+            color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..Default::default()
+        })
+        .with_children(|background| {
+            background
+                .spawn_bundle(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                        position_type: PositionType::Absolute,
+                        position: Rect {
+                            left: Val::Px(0.0),
+                            top: Val::Px(0.0),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                    color: color.into(),
+                    ..Default::default()
+                })
+                .insert(bar);
+            spawn_text(background, text_style, label);
+        });
+}
+
+fn spawn_text(parent: &mut ChildBuilder, text_style: &TextStyle, marker: impl Component) {
+    parent
+        .spawn_bundle(TextBundle {
+            text: Text::with_section("", text_style.clone(), Default::default()),
+            ..Default::default()
+        })
+        .insert(marker);
+}
+
+pub fn update_health_display(
+    player_query: Query<
+        (&Health, &MaxHealth),
+        (With<Player>, Or<(Changed<Health>, Changed<MaxHealth>)>),
+    >,
+    mut bar_query: Query<&mut Style, With<HealthBar>>,
+    mut text_query: Query<&mut Text, With<HealthText>>,
+) {
+    for (health, max_health) in player_query.iter() {
+        let fraction = (health.0 / max_health.0).clamp(0.0, 1.0);
+        for mut style in bar_query.iter_mut() {
+            style.size.width = Val::Percent(fraction * 100.0);
+        }
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value =
+                format!("{} / {}", health.0.max(0.0).ceil(), max_health.0.ceil());
+        }
+    }
+}
+
+pub fn update_experience_display(
+    xp_curve: Res<XpCurve>,
+    player_query: Query<
+        (&Experience, &Level),
+        (With<Player>, Or<(Changed<Experience>, Changed<Level>)>),
+    >,
+    mut bar_query: Query<&mut Style, With<XpBar>>,
+    mut text_query: Query<&mut Text, With<LevelText>>,
+) {
+    for (experience, level) in player_query.iter() {
+        let fraction = experience.0 as f32 / xp_curve.xp_to_next_level(level.0) as f32;
+        for mut style in bar_query.iter_mut() {
+            style.size.width = Val::Percent(fraction.min(1.0) * 100.0);
+        }
+        for mut text in text_query.iter_mut() {
+            text.sections[0].value = format!("Level {}", level.0);
+        }
+    }
+}
+
+pub fn update_run_stats_display(
+    run_time: Res<RunTime>,
+    kill_count: Res<KillCount>,
+    mut time_query: Query<&mut Text, With<RunTimeText>>,
+    mut kill_query: Query<&mut Text, (With<KillCountText>, Without<RunTimeText>)>,
+    new_text_query: Query<(), Or<(Added<RunTimeText>, Added<KillCountText>)>>,
+) {
+    // Freshly spawned labels need filling in even if the stats haven't changed
+    let spawned = new_text_query.iter().next().is_some();
+
+    if run_time.is_changed() || spawned {
+        let seconds = run_time.0.as_secs();
+        for mut text in time_query.iter_mut() {
+            text.sections[0].value = format!("{}:{:02}", seconds / 60, seconds % 60);
+        }
+    }
+
+    if kill_count.is_changed() || spawned {
+        for mut text in kill_query.iter_mut() {
+            text.sections[0].value = format!("{} kills", kill_count.0);
+        }
+    }
+}
+
+/// Rebuilds the weapon icons when a weapon is added
+pub fn update_weapon_icons(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    new_weapon_query: Query<(), Added<Weapon>>,
+    weapon_query: Query<&Weapon>,
+    icons_query: Query<Entity, With<WeaponIcons>>,
+    icon_query: Query<Entity, With<WeaponIcon>>,
+) {
+    if new_weapon_query.iter().next().is_none() {
+        return;
+    }
+
+    rebuild_weapon_icons(
+        &mut commands,
+        &font.0,
+        &weapon_query,
+        &icons_query,
+        &icon_query,
+    );
+}
+
+/// Rebuilds the weapon icons unconditionally. Levels only change while the level up screen is
+/// shown, so this runs whenever play resumes.
+pub fn refresh_weapon_icons(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    weapon_query: Query<&Weapon>,
+    icons_query: Query<Entity, With<WeaponIcons>>,
+    icon_query: Query<Entity, With<WeaponIcon>>,
+) {
+    rebuild_weapon_icons(
+        &mut commands,
+        &font.0,
+        &weapon_query,
+        &icons_query,
+        &icon_query,
+    );
+}
+
+fn rebuild_weapon_icons(
+    commands: &mut Commands,
+    font: &Handle<Font>,
+    weapon_query: &Query<&Weapon>,
+    icons_query: &Query<Entity, With<WeaponIcons>>,
+    icon_query: &Query<Entity, With<WeaponIcon>>,
+) {
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 16.0,
+        color: Color::WHITE,
+    };
+
+    for icon in icon_query.iter() {
+        commands.entity(icon).despawn_recursive();
+    }
+
+    for icons in icons_query.iter() {
+        commands.entity(icons).with_children(|icons| {
+            for weapon in weapon_query.iter() {
+                icons
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(WEAPON_ICON_SIZE), Val::Px(WEAPON_ICON_SIZE)),
+                            margin: Rect::all(Val::Px(2.0)),
+                            align_items: AlignItems::FlexEnd,
+                            justify_content: JustifyContent::FlexEnd,
+                            ..Default::default()
+                        },
+                        color: weapon.kind.color().into(),
+                        ..Default::default()
+                    })
+                    .insert(WeaponIcon)
+                    .with_children(|icon| {
+                        icon.spawn_bundle(TextBundle {
+                            text: Text::with_section(
+                                weapon.level.to_string(),
+                                text_style.clone(),
+                                Default::default(),
+                            ),
+                            ..Default::default()
+                        });
+                    });
+            }
+        });
+    }
+}
@@ -0,0 +1,108 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{
+    input::Input,
+    math::Vec2,
+    prelude::{KeyCode, OrthographicProjection, Query, Res, ResMut, With},
+    window::Windows,
+};
+
+use crate::{
+    replay::{Playback, Recorder},
+    view_half_extents, MainCamera,
+};
+
+/// Directions held down during a tick, one bit each
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Movement(pub u8);
+
+impl Movement {
+    pub const UP: u8 = 1 << 0;
+    pub const DOWN: u8 = 1 << 1;
+    pub const LEFT: u8 = 1 << 2;
+    pub const RIGHT: u8 = 1 << 3;
+
+    fn held(&self, direction: u8) -> bool {
+        self.0 & direction != 0
+    }
+
+    /// Unit vector pointing where the held directions add up to, or zero
+    pub fn direction(&self) -> Vec2 {
+        let x = self.held(Movement::RIGHT) as i8 - self.held(Movement::LEFT) as i8;
+        let y = self.held(Movement::UP) as i8 - self.held(Movement::DOWN) as i8;
+        Vec2::new(x as f32, y as f32).normalize_or_zero()
+    }
+
+    /// Whichever of the eight directions is closest to `direction`, or none for zero
+    pub fn towards(direction: Vec2) -> Self {
+        if direction == Vec2::ZERO {
+            return Movement(0);
+        }
+
+        let angle = (direction.y.atan2(direction.x) / FRAC_PI_4).round() * FRAC_PI_4;
+        let (sin, cos) = angle.sin_cos();
+
+        let mut movement = 0;
+        if cos > 0.5 {
+            movement |= Movement::RIGHT;
+        } else if cos < -0.5 {
+            movement |= Movement::LEFT;
+        }
+        if sin > 0.5 {
+            movement |= Movement::UP;
+        } else if sin < -0.5 {
+            movement |= Movement::DOWN;
+        }
+        Movement(movement)
+    }
+}
+
+/// Everything a tick reads from outside of the simulation, sampled once at its start so a
+/// recording can feed back exactly the same
+#[derive(Default)]
+pub struct TickInput {
+    pub movement: Movement,
+    /// Half the size of the area visible around the player, if there is a window to show it in
+    pub view_half_extents: Option<Vec2>,
+}
+
+/// Directions held down with WASD or the arrow keys
+pub fn keyboard_movement(keyboard_input: &Input<KeyCode>) -> Movement {
+    let pressed = |keys: [KeyCode; 2]| keys.iter().any(|key| keyboard_input.pressed(*key));
+
+    let mut movement = 0;
+    for (keys, direction) in [
+        ([KeyCode::W, KeyCode::Up], Movement::UP),
+        ([KeyCode::S, KeyCode::Down], Movement::DOWN),
+        ([KeyCode::A, KeyCode::Left], Movement::LEFT),
+        ([KeyCode::D, KeyCode::Right], Movement::RIGHT),
+    ] {
+        if pressed(keys) {
+            movement |= direction;
+        }
+    }
+    Movement(movement)
+}
+
+pub fn sample_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    recorder: Option<ResMut<Recorder>>,
+    playback: Option<ResMut<Playback>>,
+    mut tick_input: ResMut<TickInput>,
+    camera_query: Query<&OrthographicProjection, With<MainCamera>>,
+) {
+    if let Some(mut playback) = playback {
+        *tick_input = playback.next_tick();
+    } else {
+        tick_input.movement = keyboard_movement(&keyboard_input);
+        tick_input.view_half_extents = camera_query
+            .get_single()
+            .ok()
+            .and_then(|projection| view_half_extents(&windows, projection));
+    }
+
+    if let Some(mut recorder) = recorder {
+        recorder.record_tick(&tick_input);
+    }
+}
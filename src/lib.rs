@@ -0,0 +1,623 @@
+// Benchmarks use the unstable test crate, run them with `cargo +nightly bench --features bench`
+#![cfg_attr(all(test, feature = "bench"), feature(test))]
+
+use std::time::Duration;
+
+use bevy::{
+    app::{Events, Plugin, PluginGroup, PluginGroupBuilder},
+    asset::{AssetPlugin, AssetServer, Handle},
+    core::{Time, Timer},
+    diagnostic::DiagnosticsPlugin,
+    ecs::schedule::ShouldRun,
+    input::InputPlugin,
+    math::{Vec2, Vec3},
+    prelude::{
+        App, Commands, Component, CoreStage, DespawnRecursiveExt, Entity, IntoSystem,
+        OrthographicCameraBundle, OrthographicProjection, ParallelSystemDescriptorCoercion, Query,
+        Res, ResMut, Schedule, StageLabel, State, SystemLabel, SystemSet, SystemStage,
+        UiCameraBundle, With,
+    },
+    text::Font,
+    transform::{TransformPlugin, TransformSystem},
+    window::Windows,
+    MinimalPlugins,
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    physics::{
+        attach_bodies_and_colliders_system, collect_removals, finalize_collider_attach_to_bodies,
+        step_world_system, sync_transforms, NoUserData, PhysicsStages, RapierConfiguration,
+        RapierPhysicsPlugin, TimestepMode,
+    },
+    prelude::IntegrationParameters,
+};
+
+use crate::{
+    camera::{follow_player, reset_camera, zoom_camera, CameraFollow},
+    hud::{
+        refresh_weapon_icons, spawn_hud, update_experience_display, update_health_display,
+        update_run_stats_display, update_weapon_icons,
+    },
+    input::sample_input,
+    menu::{
+        despawn_menu_screen, game_over_input, main_menu_input, spawn_game_over_screen,
+        spawn_main_menu,
+    },
+    pause::{despawn_pause_screen, pause_input, pause_menu_input, spawn_pause_screen, PauseMenu},
+    replay::{finish_replay, save_replay},
+    rng::RunRng,
+};
+
+pub use crate::{
+    combat::CombatPlugin,
+    env::{Observation, VampsEnv, GRID_CELL_SIZE, GRID_SIZE, OBSERVATION_LEN},
+    headless::{simulate, Bot, Summary, DEFAULT_MINUTES},
+    input::{Movement, TickInput},
+    monster::MonsterPlugin,
+    player::PlayerPlugin,
+    replay::{Playback, Recorder},
+    terminal::{TerminalGuard, TerminalPlugin, TERMINAL_FRAME_TIME},
+    upgrade::Upgrade,
+    weapon::WeaponKind,
+    world::WorldPlugin,
+};
+
+mod archetype;
+mod camera;
+mod combat;
+mod env;
+mod headless;
+mod hud;
+mod input;
+mod menu;
+mod monster;
+mod pause;
+mod player;
+mod progression;
+mod replay;
+mod rng;
+mod spatial;
+mod spawner;
+mod terminal;
+#[cfg(test)]
+mod testing;
+mod ui;
+mod upgrade;
+mod weapon;
+mod world;
+
+#[derive(Component)]
+pub struct Player;
+
+#[derive(Component)]
+pub struct Monster;
+
+#[derive(Component)]
+pub struct Obstacle;
+
+#[derive(Component)]
+pub struct Projectile {
+    /// Kind of weapon that fired the projectile
+    weapon: WeaponKind,
+    direction: Vec3,
+    /// Units per second
+    speed: f32,
+    damage: f32,
+    /// Number of distinct monsters the projectile can hit before it is spent
+    lives: usize,
+    hits: Vec<Entity>,
+    /// Monsters this close to a hit take the projectile's damage as well
+    blast_radius: f32,
+    /// Distance the projectile may travel before it is despawned
+    range: f32,
+    travelled: f32,
+    lifetime: Timer,
+}
+
+#[derive(Component)]
+pub struct MainCamera;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    MainMenu,
+    Playing,
+    /// Gameplay is frozen behind the pause menu. Pushed on top of `Playing` like `LevelUp`.
+    Paused,
+    /// Gameplay is frozen while the player picks an upgrade. Pushed on top of `Playing`, so the
+    /// run carries on when it is popped rather than starting over.
+    LevelUp,
+    /// The player died. The run's entities stay in place behind the game over screen until it is
+    /// left.
+    GameOver,
+}
+
+/// Marks entities belonging to the current run, despawned when the run ends
+#[derive(Component)]
+pub struct RunEntity;
+
+/// Time survived in the current run, advanced with every gameplay tick
+#[derive(Default)]
+pub struct RunTime(pub Duration);
+
+/// Font shared by every piece of UI. Points at no font at all until `InterfacePlugin` loads one,
+/// which is enough for apps that draw nothing.
+#[derive(Default)]
+pub struct UiFont(pub Handle<Font>);
+
+#[derive(Component)]
+pub struct Health(pub f32);
+
+#[derive(Component)]
+pub struct MaxHealth(pub f32);
+
+/// Units per second
+#[derive(Component)]
+pub struct MoveSpeed(pub f32);
+
+/// Direction the player last moved in
+#[derive(Component)]
+pub struct Facing(pub Vec2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageCause {
+    Projectile(WeaponKind),
+    Contact,
+}
+
+/// Request to subtract health from an entity, applied by `apply_damage`
+pub struct Damage {
+    pub target: Entity,
+    pub amount: f32,
+    pub cause: DamageCause,
+}
+
+/// Sent once when an entity's health drops to zero. The entity may already be despawned by the
+/// time a reader sees it, so its last position is carried along for drops and effects.
+pub struct Died {
+    pub entity: Entity,
+    pub translation: Vec3,
+    pub cause: DamageCause,
+}
+
+#[derive(Default)]
+pub struct KillCount(pub usize);
+
+/// How new runs are started
+#[derive(Default)]
+pub struct RunSettings {
+    /// Seed every run with this instead of a random seed. A replay being played back brings its
+    /// own seed.
+    pub seed: Option<u64>,
+}
+
+/// Rate at which every gameplay system and the physics world advance
+pub const TICKS_PER_SECOND: u32 = 60;
+
+/// Upper bound on ticks run in a single frame, so a long stall doesn't snowball into more work
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub struct FixedUpdateStage;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub enum GameplayStage {
+    /// A stage of its own, so the chunks loaded around the player are in place for the rest of the
+    /// tick, e.g. for monsters to spawn clear of their obstacles
+    Input,
+    Update,
+    /// Rapier picks up bodies and colliders spawned so far. A stage of its own, so the components
+    /// it inserts are in place for `Physics`.
+    AttachBodies,
+    Physics,
+    PostPhysics,
+}
+
+/// Phases of a gameplay tick, run in the order they are declared. Systems added with
+/// `add_gameplay_system` run after every system of the phase before theirs, so e.g. velocities set
+/// during `Movement` are always applied by the `Physics` step of the same tick, and deaths are
+/// only looked at once all of the tick's damage was dealt.
+///
+/// Rapier's systems run as part of every tick rather than once per frame: bodies and colliders
+/// spawned before `Physics` join the world in time for its step, despawned ones leave it, and
+/// `Transform`s catch up with it right after. Every tick sees the same world, however many ticks
+/// the frame runs. Rapier's own `PhysicsSystems::StepWorld` in `CoreStage::Update` never advances
+/// the world, see `resume_physics`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, SystemLabel)]
+pub enum TickPhase {
+    /// Advances the run clock, fills in `TickInput` and rebuilds the spatial indexes, for the rest
+    /// of the tick to work from
+    Input,
+    /// The world around the player is loaded, as far out as the view sampled during `Input` needs
+    World,
+    /// Monsters spawn, and weapons pick their targets and fire
+    Ai,
+    /// The player, monsters, projectiles and gems move. Bodies only get their velocities set here,
+    /// the physics step moves them.
+    Movement,
+    /// Rapier attaches new bodies, drops despawned ones, steps the physics world and copies the
+    /// result to `Transform`s, in stages of its own
+    Physics,
+    /// Projectile hits and contact with monsters from the step turn into damage and deaths
+    Combat,
+    /// The dead are despawned and drop their gems, spent projectiles are despawned and the run
+    /// ends if the player died
+    Cleanup,
+}
+
+impl TickPhase {
+    fn stage(self) -> GameplayStage {
+        match self {
+            TickPhase::Input | TickPhase::World => GameplayStage::Input,
+            TickPhase::Ai | TickPhase::Movement => GameplayStage::Update,
+            TickPhase::Physics => GameplayStage::Physics,
+            TickPhase::Combat | TickPhase::Cleanup => GameplayStage::PostPhysics,
+        }
+    }
+
+    /// Phase running right before this one in the same stage. Phases in different stages are
+    /// already ordered by their stages.
+    fn previous(self) -> Option<TickPhase> {
+        match self {
+            TickPhase::World => Some(TickPhase::Input),
+            TickPhase::Movement => Some(TickPhase::Ai),
+            TickPhase::Cleanup => Some(TickPhase::Combat),
+            TickPhase::Input | TickPhase::Ai | TickPhase::Physics | TickPhase::Combat => None,
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum CombatSystem {
+    /// Systems sending `Damage` events
+    DealDamage,
+    /// Turns `Damage` into health loss and `Died` events, for `TickPhase::Cleanup` to act on
+    ApplyDamage,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum RunSystem {
+    /// Resets the bookkeeping shared by the whole run and seeds `RunRng`. The plugins reset their
+    /// own state after it.
+    Start,
+    /// Pushes `AppState::LevelUp` over `Playing` once the player levelled up. Systems pushing
+    /// other states over `Playing` run after it, and leave the level up to go first.
+    QueueLevelUps,
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+enum PhysicsStep {
+    Finalize,
+    Resume,
+    Step,
+}
+
+/// Clock driving `FixedUpdateStage`, fed with real frame time by `accumulate_frame_time`
+pub struct FixedTime {
+    step: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTime {
+    fn new(ticks_per_second: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / ticks_per_second,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    fn delta_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Runs no more ticks this frame, so state changes asked for during the current tick take
+    /// effect before the next one. Keeps the simulation independent of how many ticks a frame
+    /// happens to run.
+    fn interrupt(&mut self) {
+        self.accumulator = Duration::ZERO;
+    }
+}
+
+/// Adding systems to a phase of the gameplay tick, which runs as a schedule of its own inside of
+/// `FixedUpdateStage`
+pub trait AddGameplaySystem {
+    fn add_gameplay_system<Params>(
+        &mut self,
+        phase: TickPhase,
+        system: impl ParallelSystemDescriptorCoercion<Params>,
+    ) -> &mut Self;
+
+    /// Adds an event sent and read during the gameplay tick. Unlike with `App::add_event`, its
+    /// buffers are swapped every tick rather than every frame, so events reach their readers the
+    /// same way however many ticks a frame runs, including none.
+    fn add_gameplay_event<T: Send + Sync + 'static>(&mut self) -> &mut Self;
+}
+
+impl AddGameplaySystem for App {
+    fn add_gameplay_system<Params>(
+        &mut self,
+        phase: TickPhase,
+        system: impl ParallelSystemDescriptorCoercion<Params>,
+    ) -> &mut Self {
+        let system = system.label(phase);
+        let system = match phase.previous() {
+            Some(previous) => system.after(previous),
+            None => system,
+        };
+
+        self.stage(FixedUpdateStage, |schedule: &mut Schedule| {
+            schedule.add_system_to_stage(phase.stage(), system)
+        })
+    }
+
+    fn add_gameplay_event<T: Send + Sync + 'static>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>()
+            .add_gameplay_system(TickPhase::Input, Events::<T>::update_system)
+    }
+}
+
+/// Everything the simulation needs from bevy, without a window or rendering. Stands in for
+/// `DefaultPlugins` in headless apps.
+pub struct HeadlessPlugins;
+
+impl PluginGroup for HeadlessPlugins {
+    fn build(&mut self, group: &mut PluginGroupBuilder) {
+        MinimalPlugins.build(group);
+        group
+            .add(TransformPlugin)
+            .add(DiagnosticsPlugin)
+            .add(InputPlugin)
+            .add(AssetPlugin);
+    }
+}
+
+/// The gameplay simulation: runs on a fixed timestep, with physics, made up of `PlayerPlugin`,
+/// `MonsterPlugin`, `CombatPlugin` and `WorldPlugin`. Needs either `DefaultPlugins` or
+/// `HeadlessPlugins` added before it.
+///
+/// Nothing in it fills in `TickInput`. That is left to a system in `TickPhase::Input`, added by
+/// e.g. `InterfacePlugin` or `TerminalPlugin`.
+pub struct VampsPlugin;
+
+impl Plugin for VampsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+            .insert_resource(FixedTime::new(TICKS_PER_SECOND))
+            .init_resource::<TickInput>()
+            .add_state(AppState::MainMenu)
+            .init_resource::<RunSettings>()
+            .init_resource::<KillCount>()
+            .init_resource::<RunRng>()
+            .init_resource::<RunTime>()
+            .init_resource::<UiFont>()
+            .add_startup_system(setup)
+            .add_system(finish_replay)
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(end_run)
+                    .with_system(start_run.label(RunSystem::Start)),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::GameOver).with_system(save_replay))
+            // After `CoreStage::Update`. Rapier's own once-per-frame copy to `Transform` after it
+            // copies the positions the last tick already did.
+            .add_stage_before(
+                PhysicsStages::SyncTransforms,
+                FixedUpdateStage,
+                Schedule::default()
+                    .with_run_criteria(fixed_timestep.system())
+                    .with_stage(GameplayStage::Input, SystemStage::parallel())
+                    .with_stage(GameplayStage::Update, SystemStage::parallel())
+                    .with_stage(
+                        GameplayStage::AttachBodies,
+                        SystemStage::single_threaded().with_system(
+                            attach_bodies_and_colliders_system.label(TickPhase::Physics),
+                        ),
+                    )
+                    .with_stage(
+                        GameplayStage::Physics,
+                        SystemStage::single_threaded()
+                            .with_system(
+                                finalize_collider_attach_to_bodies
+                                    .label(TickPhase::Physics)
+                                    .label(PhysicsStep::Finalize),
+                            )
+                            .with_system(
+                                collect_removals
+                                    .label(TickPhase::Physics)
+                                    .label(PhysicsStep::Finalize),
+                            )
+                            .with_system(
+                                resume_physics
+                                    .label(TickPhase::Physics)
+                                    .label(PhysicsStep::Resume)
+                                    .after(PhysicsStep::Finalize),
+                            )
+                            .with_system(
+                                step_world_system::<NoUserData>
+                                    .label(TickPhase::Physics)
+                                    .label(PhysicsStep::Step)
+                                    .after(PhysicsStep::Resume),
+                            )
+                            .with_system(
+                                suspend_physics
+                                    .label(TickPhase::Physics)
+                                    .after(PhysicsStep::Step),
+                            )
+                            .with_system(
+                                sync_transforms
+                                    .label(TickPhase::Physics)
+                                    .after(PhysicsStep::Step),
+                            ),
+                    )
+                    .with_stage(GameplayStage::PostPhysics, SystemStage::parallel()),
+            )
+            .add_gameplay_system(TickPhase::Input, advance_run_time)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(MonsterPlugin)
+            .add_plugin(CombatPlugin)
+            .add_plugin(WorldPlugin);
+    }
+}
+
+/// Everything needed to play in a window on top of `VampsPlugin`: keyboard input, menus, the HUD
+/// and the camera
+pub struct InterfacePlugin;
+
+impl Plugin for InterfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PauseMenu>()
+            .add_startup_system(setup_interface)
+            .add_system_to_stage(CoreStage::PreUpdate, accumulate_frame_time)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                follow_player.before(TransformSystem::TransformPropagate),
+            )
+            .add_gameplay_system(TickPhase::Input, sample_input)
+            .add_system_set(
+                SystemSet::on_enter(AppState::MainMenu)
+                    .with_system(spawn_main_menu)
+                    .with_system(save_replay)
+                    .with_system(end_run),
+            )
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(main_menu_input))
+            .add_system_set(SystemSet::on_exit(AppState::MainMenu).with_system(despawn_menu_screen))
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_hud)
+                    .with_system(reset_camera),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(pause_input.after(RunSystem::QueueLevelUps))
+                    .with_system(zoom_camera)
+                    .with_system(update_health_display)
+                    .with_system(update_experience_display)
+                    .with_system(update_run_stats_display)
+                    .with_system(update_weapon_icons),
+            )
+            .add_system_set(
+                SystemSet::on_resume(AppState::Playing).with_system(refresh_weapon_icons),
+            )
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(spawn_pause_screen))
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(pause_menu_input))
+            .add_system_set(SystemSet::on_exit(AppState::Paused).with_system(despawn_pause_screen))
+            .add_system_set(
+                SystemSet::on_enter(AppState::GameOver).with_system(spawn_game_over_screen),
+            )
+            .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(game_over_input))
+            .add_system_set(
+                SystemSet::on_exit(AppState::GameOver).with_system(despawn_menu_screen),
+            );
+    }
+}
+
+fn accumulate_frame_time(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    mut fixed_time: ResMut<FixedTime>,
+) {
+    // Time outside of play doesn't count towards the simulation
+    if *state.current() != AppState::Playing {
+        return;
+    }
+
+    let max_accumulated = fixed_time.step * MAX_TICKS_PER_FRAME;
+    fixed_time.accumulator = (fixed_time.accumulator + time.delta()).min(max_accumulated);
+}
+
+fn fixed_timestep(
+    state: Res<State<AppState>>,
+    playback: Option<Res<Playback>>,
+    mut fixed_time: ResMut<FixedTime>,
+) -> ShouldRun {
+    // Ticks stop as soon as the game leaves play, e.g. for a level up earlier in the frame
+    if *state.current() != AppState::Playing {
+        return ShouldRun::No;
+    }
+
+    // A replay ends with the last recorded tick, so the result can be checked
+    if playback.map_or(false, |playback| playback.is_finished()) {
+        return ShouldRun::No;
+    }
+
+    if fixed_time.accumulator >= fixed_time.step {
+        fixed_time.accumulator -= fixed_time.step;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+// Rapier runs its own step once per frame during `CoreStage::Update`. The pipeline is kept
+// inactive outside of `GameplayStage::Physics` so the world only ever advances in lockstep with
+// the gameplay tick, using the fixed `IntegrationParameters::dt` set up in `setup`.
+fn resume_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = true;
+}
+
+fn suspend_physics(mut rapier_config: ResMut<RapierConfiguration>) {
+    rapier_config.physics_pipeline_active = false;
+}
+
+fn setup(
+    fixed_time: Res<FixedTime>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut integration_parameters: ResMut<IntegrationParameters>,
+) {
+    rapier_config.gravity = Vector2::zeros();
+    rapier_config.timestep_mode = TimestepMode::FixedTimestep;
+    rapier_config.physics_pipeline_active = false;
+    integration_parameters.dt = fixed_time.delta_seconds();
+}
+
+fn setup_interface(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(OrthographicCameraBundle::new_2d())
+        .insert(MainCamera)
+        .insert(CameraFollow::default());
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands.insert_resource(UiFont(asset_server.load("fonts/DejaVuSans-Bold.ttf")));
+}
+
+/// Resets the run's bookkeeping and picks its seed. The player and the world around them are
+/// spawned by the plugins.
+fn start_run(
+    settings: Res<RunSettings>,
+    playback: Option<Res<Playback>>,
+    recorder: Option<ResMut<Recorder>>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut run_rng: ResMut<RunRng>,
+    mut run_time: ResMut<RunTime>,
+    mut kill_count: ResMut<KillCount>,
+) {
+    fixed_time.accumulator = Duration::ZERO;
+    run_time.0 = Duration::ZERO;
+    kill_count.0 = 0;
+
+    let seed = match playback {
+        Some(playback) => playback.seed(),
+        None => settings.seed.unwrap_or_else(rand::random),
+    };
+    *run_rng = RunRng::new(seed);
+    if let Some(mut recorder) = recorder {
+        recorder.start(seed);
+    }
+}
+
+/// Despawns everything left over from the last run, if there was one
+fn end_run(mut commands: Commands, run_query: Query<Entity, With<RunEntity>>) {
+    for entity in run_query.iter() {
+        // Takes the player's weapons along with it
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn advance_run_time(fixed_time: Res<FixedTime>, mut run_time: ResMut<RunTime>) {
+    run_time.0 += fixed_time.step;
+}
+
+/// Half the size of the area visible through the camera, if there is a window to show it in
+fn view_half_extents(windows: &Windows, projection: &OrthographicProjection) -> Option<Vec2> {
+    windows
+        .get_primary()
+        .map(|window| Vec2::new(window.width(), window.height()) / 2.0 * projection.scale)
+}
@@ -1,266 +1,107 @@
-use std::time::Duration;
+use std::path::PathBuf;
 
-use bevy::{
-    core::{Time, Timer},
-    input::Input,
-    math::{Vec2, Vec3},
-    prelude::{
-        App, Color, Commands, Component, Entity, KeyCode, OrthographicCameraBundle, Query, Res,
-        ResMut, Transform, With,
-    },
-    sprite::{Sprite, SpriteBundle},
-    DefaultPlugins,
+use bevy::{app::ScheduleRunnerSettings, prelude::App, DefaultPlugins};
+use vamps::{
+    simulate, HeadlessPlugins, InterfacePlugin, Playback, Recorder, RunSettings, TerminalGuard,
+    TerminalPlugin, VampsPlugin, DEFAULT_MINUTES, TERMINAL_FRAME_TIME,
 };
-use bevy_rapier2d::{
-    na::Vector2,
-    physics::{
-        ColliderBundle, ColliderPositionSync, IntoHandle, NoUserData, RapierConfiguration,
-        RapierPhysicsPlugin, RigidBodyBundle,
-    },
-    prelude::{
-        CoefficientCombineRule, ColliderMaterial, ColliderPositionComponent, ColliderShape,
-        NarrowPhase, RigidBodyMassPropsFlags, RigidBodyType, RigidBodyVelocityComponent,
-    },
-};
-use rand::prelude::IteratorRandom;
-
-#[derive(Component)]
-struct Player;
-
-#[derive(Component)]
-struct Monster;
 
-#[derive(Component)]
-struct Obstacle;
-
-#[derive(Component)]
-struct Projectile {
-    direction: Vec3,
-    lives: usize,
+/// Options given on the command line
+#[derive(Default)]
+struct Args {
+    /// `--seed <seed>`: seed every run with this instead of a random seed
+    seed: Option<u64>,
+    /// `--record <path>`: save a replay of every run to this file, overwriting the last one
+    record: Option<PathBuf>,
+    /// `--replay <path>`: play the replay saved to this file back, and check it plays out the same
+    replay: Option<PathBuf>,
+    /// `--headless`: simulate a single run as fast as possible without a window, played by a bot,
+    /// then print a summary of it as JSON
+    headless: bool,
+    /// `--minutes <minutes>`: simulated minutes a headless run lasts at most
+    minutes: Option<f32>,
+    /// `--terminal`: play in the terminal rather than in a window
+    terminal: bool,
 }
 
-struct AttackTimer(Timer);
-
-#[derive(Component)]
-struct Health(f32);
+impl Args {
+    fn parse() -> Self {
+        let mut parsed = Args::default();
+        let mut args = std::env::args().skip(1);
 
-fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
-        .insert_resource(AttackTimer(Timer::new(Duration::from_millis(500), true)))
-        .add_startup_system(setup)
-        .add_system(player_attack)
-        .add_system(projectile_movement)
-        .add_system(player_movement)
-        .add_system(monster_movement)
-        .add_system(player_damage)
-        .add_system(player_death)
-        .run();
-}
-
-fn setup(mut commands: Commands, mut rapier_config: ResMut<RapierConfiguration>) {
-    rapier_config.gravity = Vector2::zeros();
-
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
-
-    commands
-        .spawn_bundle(SpriteBundle {
-            sprite: Sprite {
-                color: Color::rgb(0.5, 0.5, 1.0),
-                custom_size: Some(Vec2::new(50.0, 50.0)),
-                ..Default::default()
-            },
-            ..Default::default()
-        })
-        .insert_bundle(RigidBodyBundle {
-            body_type: RigidBodyType::KinematicVelocityBased.into(),
-            ..RigidBodyBundle::default()
-        })
-        .insert_bundle(ColliderBundle {
-            position: [0.0, 0.0].into(),
-            shape: ColliderShape::cuboid(50.0 / 2.0, 50.0 / 2.0).into(),
-            material: ColliderMaterial {
-                friction: 0.0,
-                friction_combine_rule: CoefficientCombineRule::Min,
-                restitution: 0.0,
-                ..Default::default()
-            }
-            .into(),
-            ..Default::default()
-        })
-        .insert(ColliderPositionSync::Discrete)
-        .insert(Player)
-        .insert(Health(100.0));
-
-    for pos in [Vector2::new(100.0, 215.0), Vector2::new(-100.0, 215.0)] {
-        commands
-            .spawn_bundle(SpriteBundle {
-                sprite: Sprite {
-                    color: Color::rgb(0.5, 0.5, 0.5),
-                    custom_size: Some(Vec2::new(50.0, 50.0)),
-                    ..Default::default()
-                },
-                ..Default::default()
-            })
-            .insert_bundle(RigidBodyBundle {
-                mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
-                ..RigidBodyBundle::default()
-            })
-            .insert_bundle(ColliderBundle {
-                position: pos.into(),
-                shape: ColliderShape::cuboid(
-                    50.0 / rapier_config.scale / 2.0,
-                    50.0 / rapier_config.scale / 2.0,
-                )
-                .into(),
-                material: ColliderMaterial {
-                    friction: 0.0,
-                    friction_combine_rule: CoefficientCombineRule::Min,
-                    restitution: 1.0,
-                    ..Default::default()
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--seed" => {
+                    let seed = args.next().expect("--seed should be followed by a seed");
+                    parsed.seed = Some(seed.parse().expect("seed should be a whole number"));
                 }
-                .into(),
-                ..Default::default()
-            })
-            .insert(ColliderPositionSync::Discrete)
-            .insert(Monster)
-            .insert(Health(20.0));
-    }
-
-    let pos = [-250.0, 250.0];
-    for x in pos {
-        for y in pos {
-            commands
-                .spawn_bundle(SpriteBundle {
-                    transform: Transform {
-                        translation: Vec3::new(x, y, 0.0),
-                        scale: Vec3::new(50.0, 50.0, 0.0),
-                        ..Default::default()
-                    },
-                    sprite: Sprite {
-                        color: Color::rgb(0.5, 0.2, 0.2),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(Obstacle);
+                "--record" => {
+                    let path = args.next().expect("--record should be followed by a path");
+                    parsed.record = Some(path.into());
+                }
+                "--replay" => {
+                    let path = args.next().expect("--replay should be followed by a path");
+                    parsed.replay = Some(path.into());
+                }
+                "--headless" => parsed.headless = true,
+                "--terminal" => parsed.terminal = true,
+                "--minutes" => {
+                    let minutes = args
+                        .next()
+                        .expect("--minutes should be followed by a number");
+                    parsed.minutes = Some(minutes.parse().expect("minutes should be a number"));
+                }
+                _ => panic!("unknown argument {}", arg),
+            }
         }
-    }
-}
-
-fn player_attack(
-    mut commands: Commands,
-    time: Res<Time>,
-    mut attack_timer: ResMut<AttackTimer>,
-    player_transform_query: Query<&Transform, With<Player>>,
-    monsters_transform_query: Query<&Transform, With<Monster>>,
-) {
-    // Attack when the timer elapses
-    if attack_timer.0.tick(time.delta()).finished() {
-        // Find player location
-        let player_translation = player_transform_query.single().translation;
 
-        // Find random monster in scene
-        let monster_transform = monsters_transform_query
-            .iter()
-            .choose(&mut rand::thread_rng());
-
-        // Only spawn a projectile if any monster is present
-        if let Some(monster_transform) = monster_transform {
-            let direction = (monster_transform.translation - player_translation).normalize();
-
-            // Spawn a new projectile
-            commands
-                .spawn_bundle(SpriteBundle {
-                    transform: Transform {
-                        translation: player_translation + direction * 28.0,
-                        scale: Vec3::new(10.0, 10.0, 0.0),
-                        ..Default::default()
-                    },
-                    sprite: Sprite {
-                        color: Color::rgb(0.2, 0.5, 0.2),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                })
-                .insert(Projectile {
-                    direction,
-                    lives: 1,
-                });
+        // Replays are only recorded and played back in a window
+        let replays = parsed.record.is_some() || parsed.replay.is_some();
+        if replays && (parsed.headless || parsed.terminal) {
+            panic!("--record and --replay can't be combined with --headless or --terminal");
         }
-    }
-}
 
-fn projectile_movement(mut projectile_transform_query: Query<(&mut Transform, &Projectile)>) {
-    for (mut projectile_transform, projectile) in projectile_transform_query.iter_mut() {
-        projectile_transform.translation += projectile.direction * 4.0;
+        parsed
     }
 }
 
-fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut player_transform_query: Query<&mut RigidBodyVelocityComponent, With<Player>>,
-) {
-    let up = keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up);
-    let down = keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down);
-    let left = keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left);
-    let right = keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right);
-
-    let x_axis = -(left as i8) + right as i8;
-    let y_axis = -(down as i8) + up as i8;
-
-    let mut direction = Vector2::new(x_axis as f32, y_axis as f32);
-    if direction != Vector2::zeros() {
-        direction /= direction.magnitude();
-    }
-
-    for mut rb_vels in player_transform_query.iter_mut() {
-        rb_vels.linvel = direction * 150.0;
+fn main() {
+    let args = Args::parse();
+    if args.headless {
+        let seed = args.seed.unwrap_or_else(rand::random);
+        let summary = simulate(seed, args.minutes.unwrap_or(DEFAULT_MINUTES));
+        println!(
+            "{}",
+            serde_json::to_string(&summary).expect("summary should serialize")
+        );
+        return;
     }
-}
 
-fn player_damage(
-    time: Res<Time>,
-    narrow_phase: Res<NarrowPhase>,
-    monsters: Query<Entity, With<Monster>>,
-    mut player: Query<(Entity, &mut Health), With<Player>>,
-) {
-    for (player, mut health) in player.iter_mut() {
-        for monster in monsters.iter() {
-            if let Some(contact) = narrow_phase.contact_pair(player.handle(), monster.handle()) {
-                if contact.has_any_active_contact {
-                    health.0 -= 20.0 * time.delta_seconds();
-                }
-            }
-        }
+    let mut app = App::new();
+    app.insert_resource(RunSettings { seed: args.seed });
+
+    if args.terminal {
+        let _guard = TerminalGuard::enter().expect("terminal should support raw mode");
+        app.insert_resource(ScheduleRunnerSettings::run_loop(TERMINAL_FRAME_TIME))
+            .add_plugins(HeadlessPlugins)
+            .add_plugin(VampsPlugin)
+            .add_plugin(TerminalPlugin)
+            .run();
+        return;
     }
-}
 
-fn player_death(mut player_health: Query<&mut Health, With<Player>>) {
-    let mut player_health = player_health.single_mut();
+    app.add_plugins(DefaultPlugins)
+        .add_plugin(VampsPlugin)
+        .add_plugin(InterfacePlugin);
 
-    if player_health.0 <= 0.0 {
-        println!("player dead, resetting");
-        player_health.0 = 100.0;
+    if let Some(path) = &args.record {
+        app.insert_resource(Recorder::new(path.clone()));
     }
-}
-
-fn monster_movement(
-    player_transform_query: Query<&ColliderPositionComponent, With<Player>>,
-    mut monster_transform_query: Query<
-        (&ColliderPositionComponent, &mut RigidBodyVelocityComponent),
-        With<Monster>,
-    >,
-) {
-    let player_transform = player_transform_query.single();
-
-    for (position, mut velocity) in monster_transform_query.iter_mut() {
-        let mut direction = player_transform.0.translation.vector - position.0.translation.vector;
-        if direction != Vector2::zeros() {
-            direction /= direction.magnitude();
-        }
-
-        velocity.linvel = direction * 150.0 * 0.35;
+    if let Some(path) = &args.replay {
+        let playback = Playback::load(path)
+            .unwrap_or_else(|err| panic!("couldn't load replay {}: {}", path.display(), err));
+        app.insert_resource(playback);
     }
+
+    app.run();
 }
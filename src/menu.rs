@@ -0,0 +1,135 @@
+use bevy::{
+    app::AppExit,
+    asset::AssetServer,
+    input::Input,
+    prelude::{
+        AlignItems, Color, Commands, Component, DespawnRecursiveExt, Entity, EventWriter,
+        GamepadButton, GamepadButtonType, Gamepads, KeyCode, Query, Rect, Res, ResMut, Size, State,
+        Style, Text, TextBundle, Val, With,
+    },
+};
+
+use crate::{
+    archetype::MonsterArchetypes,
+    progression::Level,
+    replay::Playback,
+    rng::RunRng,
+    ui::{menu_pressed, spawn_panel, spawn_title, text_style},
+    AppState, KillCount, Player, RunTime, UiFont,
+};
+
+/// Root node of the main menu or the game over screen
+#[derive(Component)]
+pub struct MenuScreen;
+
+pub fn spawn_main_menu(mut commands: Commands, font: Res<UiFont>) {
+    spawn_screen(
+        &mut commands,
+        &font,
+        "vamps",
+        &["Enter / A: Start".to_string(), "Esc: Quit".to_string()],
+    );
+}
+
+pub fn spawn_game_over_screen(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    run_time: Res<RunTime>,
+    kill_count: Res<KillCount>,
+    run_rng: Res<RunRng>,
+    player_query: Query<&Level, With<Player>>,
+) {
+    let seconds = run_time.0.as_secs();
+    let level = player_query.single();
+
+    spawn_screen(
+        &mut commands,
+        &font,
+        "Game over",
+        &[
+            format!("Survived {}:{:02}", seconds / 60, seconds % 60),
+            format!("Reached level {}", level.0),
+            format!("{} kills", kill_count.0),
+            format!("Seed {}", run_rng.seed()),
+            String::new(),
+            "Enter / A: Play again".to_string(),
+            "Esc / B: Main menu".to_string(),
+        ],
+    );
+}
+
+pub fn despawn_menu_screen(mut commands: Commands, screen_query: Query<Entity, With<MenuScreen>>) {
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn main_menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    asset_server: Res<AssetServer>,
+    archetypes: Res<MonsterArchetypes>,
+    playback: Option<Res<Playback>>,
+    mut state: ResMut<State<AppState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let pressed = menu_pressed(&keyboard_input, &gamepads, &gamepad_input);
+
+    // Replays start right away. Either way, waiting for the monsters to load keeps the first
+    // ticks of a run the same every time.
+    let start = playback.is_some() || pressed(KeyCode::Return, GamepadButtonType::South);
+    if start && archetypes.loaded(&asset_server) {
+        state.set(AppState::Playing).unwrap();
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_exit_events.send(AppExit);
+    }
+}
+
+pub fn game_over_input(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    mut state: ResMut<State<AppState>>,
+) {
+    let pressed = menu_pressed(&keyboard_input, &gamepads, &gamepad_input);
+
+    if pressed(KeyCode::Return, GamepadButtonType::South) {
+        state.set(AppState::Playing).unwrap();
+    } else if pressed(KeyCode::Escape, GamepadButtonType::East) {
+        state.set(AppState::MainMenu).unwrap();
+
+        // The main menu runs in the same frame and would take Escape as quitting
+        keyboard_input.reset(KeyCode::Escape);
+    }
+}
+
+/// Full screen panel with a title over a column of lines
+fn spawn_screen(commands: &mut Commands, font: &UiFont, title: &str, lines: &[String]) {
+    let line_style = text_style(font, 28.0);
+
+    spawn_panel(
+        commands,
+        Color::rgba(0.0, 0.0, 0.0, 0.8),
+        Color::NONE,
+        AlignItems::Center,
+        |panel| {
+            spawn_title(panel, title, text_style(font, 64.0));
+
+            for line in lines {
+                panel.spawn_bundle(TextBundle {
+                    style: Style {
+                        margin: Rect::all(Val::Px(5.0)),
+                        // Keeps blank lines as spacers
+                        min_size: Size::new(Val::Auto, Val::Px(line_style.font_size)),
+                        ..Default::default()
+                    },
+                    text: Text::with_section(line.as_str(), line_style.clone(), Default::default()),
+                    ..Default::default()
+                });
+            }
+        },
+    )
+    .insert(MenuScreen);
+}
@@ -0,0 +1,108 @@
+use bevy::{
+    app::Plugin,
+    asset::{AddAsset, Assets},
+    prelude::{
+        App, Commands, DespawnRecursiveExt, Entity, EventReader, Query, Res, ResMut, SystemSet,
+        With,
+    },
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    prelude::{ColliderPositionComponent, RigidBodyVelocityComponent},
+};
+
+use crate::{
+    archetype::{
+        load_monster_archetypes, MonsterArchetype, MonsterArchetypeLoader, MonsterArchetypes,
+        MonsterKind,
+    },
+    spatial::{index_entities, SpatialIndex, MONSTER_CELL_SIZE},
+    spawner::{reset_spawner, spawn_monsters, MonsterSpawner},
+    AddGameplaySystem, AppState, Died, KillCount, Monster, Player, TickPhase,
+};
+
+/// Monsters: their archetypes loaded from assets, the waves they spawn in, chasing the player and
+/// dying
+pub struct MonsterPlugin;
+
+impl Plugin for MonsterPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<MonsterArchetype>()
+            .init_asset_loader::<MonsterArchetypeLoader>()
+            .init_resource::<MonsterArchetypes>()
+            .init_resource::<MonsterSpawner>()
+            .insert_resource(SpatialIndex::<Monster>::new(MONSTER_CELL_SIZE))
+            .add_startup_system(load_monster_archetypes)
+            .add_system_set(SystemSet::on_enter(AppState::Playing).with_system(reset_spawner))
+            .add_gameplay_system(TickPhase::Input, index_entities::<Monster>)
+            .add_gameplay_system(TickPhase::Ai, spawn_monsters)
+            .add_gameplay_system(TickPhase::Movement, monster_movement)
+            .add_gameplay_system(TickPhase::Cleanup, monster_death);
+    }
+}
+
+fn monster_death(
+    mut commands: Commands,
+    mut died_events: EventReader<Died>,
+    mut kill_count: ResMut<KillCount>,
+    monsters: Query<Entity, With<Monster>>,
+) {
+    for died in died_events.iter() {
+        if monsters.get(died.entity).is_ok() {
+            // Removing the entity also removes its rigid body and collider from the physics world
+            commands.entity(died.entity).despawn_recursive();
+            kill_count.0 += 1;
+        }
+    }
+}
+
+fn monster_movement(
+    archetypes: Res<Assets<MonsterArchetype>>,
+    player_transform_query: Query<&ColliderPositionComponent, With<Player>>,
+    mut monster_transform_query: Query<
+        (
+            &MonsterKind,
+            &ColliderPositionComponent,
+            &mut RigidBodyVelocityComponent,
+        ),
+        With<Monster>,
+    >,
+) {
+    let player_transform = player_transform_query.single();
+
+    for (kind, position, mut velocity) in monster_transform_query.iter_mut() {
+        let archetype = kind.archetype(&archetypes);
+
+        let mut direction = player_transform.0.translation.vector - position.0.translation.vector;
+        if direction != Vector2::zeros() {
+            direction /= direction.magnitude();
+        }
+
+        velocity.linvel = direction * archetype.speed;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use crate::{testing::TestApp, Damage, DamageCause, Health, KillCount};
+
+    #[test]
+    fn killed_monster_is_despawned_and_counted() {
+        let mut app = TestApp::new();
+        app.spawn_player(Vec2::ZERO);
+        let zombie = app.spawn_monster("Zombie", Vec2::new(300.0, 0.0));
+        let health = app.get::<Health>(zombie).unwrap().0;
+
+        app.send(Damage {
+            target: zombie,
+            amount: health,
+            cause: DamageCause::Contact,
+        });
+        app.tick(1);
+
+        assert!(!app.exists(zombie));
+        assert_eq!(app.resource::<KillCount>().0, 1);
+    }
+}
@@ -0,0 +1,267 @@
+use bevy::{
+    input::Input,
+    prelude::{
+        AlignItems, Color, Commands, Component, DespawnRecursiveExt, Entity, GamepadButton,
+        GamepadButtonType, Gamepads, KeyCode, Query, Res, ResMut, State, UiColor, With,
+    },
+    window::{WindowMode, Windows},
+};
+
+use crate::{
+    ui::{button_color, menu_pressed, spawn_button, spawn_panel, spawn_title, text_style},
+    upgrade::PendingLevelUps,
+    AppState, UiFont,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PauseItem {
+    Resume,
+    Options,
+    QuitToMainMenu,
+    Fullscreen,
+    VSync,
+    Back,
+}
+
+impl PauseItem {
+    fn label(&self, windows: &Windows) -> String {
+        let on_off = |on| if on { "On" } else { "Off" };
+        let window = windows.get_primary();
+
+        match self {
+            PauseItem::Resume => "Resume".to_string(),
+            PauseItem::Options => "Options".to_string(),
+            PauseItem::QuitToMainMenu => "Quit to main menu".to_string(),
+            PauseItem::Fullscreen => format!(
+                "Fullscreen: {}",
+                on_off(window.map_or(false, |window| window.mode() != WindowMode::Windowed))
+            ),
+            PauseItem::VSync => format!(
+                "VSync: {}",
+                on_off(window.map_or(false, |window| window.vsync()))
+            ),
+            PauseItem::Back => "Back".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum PausePage {
+    Main,
+    Options,
+}
+
+impl PausePage {
+    fn title(&self) -> &'static str {
+        match self {
+            PausePage::Main => "Paused",
+            PausePage::Options => "Options",
+        }
+    }
+
+    fn items(&self) -> &'static [PauseItem] {
+        match self {
+            PausePage::Main => &[
+                PauseItem::Resume,
+                PauseItem::Options,
+                PauseItem::QuitToMainMenu,
+            ],
+            PausePage::Options => &[PauseItem::Fullscreen, PauseItem::VSync, PauseItem::Back],
+        }
+    }
+}
+
+pub struct PauseMenu {
+    page: PausePage,
+    selected: usize,
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self {
+            page: PausePage::Main,
+            selected: 0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PauseScreen;
+
+#[derive(Component)]
+pub struct PauseOption(usize);
+
+/// Pauses the run on Escape or a gamepad's Start button, unless a level up is about to take over
+pub fn pause_input(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_input: ResMut<Input<GamepadButton>>,
+    pending: Res<PendingLevelUps>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if !pending.0.is_empty() {
+        return;
+    }
+
+    let pressed = menu_pressed(&keyboard_input, &gamepads, &gamepad_input)(
+        KeyCode::Escape,
+        GamepadButtonType::Start,
+    );
+
+    // Leaves the press to whatever state change was asked for first
+    if pressed && state.push(AppState::Paused).is_ok() {
+        reset_pause_buttons(&mut keyboard_input, &gamepads, &mut gamepad_input);
+    }
+}
+
+pub fn spawn_pause_screen(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    windows: Res<Windows>,
+    mut menu: ResMut<PauseMenu>,
+) {
+    *menu = PauseMenu::default();
+    spawn_page(&mut commands, &font, &windows, &menu);
+}
+
+pub fn despawn_pause_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<PauseScreen>>,
+) {
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pause_menu_input(
+    mut commands: Commands,
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_input: ResMut<Input<GamepadButton>>,
+    font: Res<UiFont>,
+    mut windows: ResMut<Windows>,
+    mut menu: ResMut<PauseMenu>,
+    mut state: ResMut<State<AppState>>,
+    screen_query: Query<Entity, With<PauseScreen>>,
+    mut option_query: Query<(&PauseOption, &mut UiColor)>,
+) {
+    let pressed = menu_pressed(&keyboard_input, &gamepads, &gamepad_input);
+
+    let count = menu.page.items().len();
+    if pressed(KeyCode::Up, GamepadButtonType::DPadUp) {
+        menu.selected = (menu.selected + count - 1) % count;
+    }
+    if pressed(KeyCode::Down, GamepadButtonType::DPadDown) {
+        menu.selected = (menu.selected + 1) % count;
+    }
+
+    // Backing out of the options goes to the main page, backing out of that resumes the run
+    let back = pressed(KeyCode::Escape, GamepadButtonType::Start)
+        || pressed(KeyCode::Escape, GamepadButtonType::East);
+    let chosen = if back {
+        Some(match menu.page {
+            PausePage::Main => PauseItem::Resume,
+            PausePage::Options => PauseItem::Back,
+        })
+    } else if pressed(KeyCode::Return, GamepadButtonType::South) {
+        Some(menu.page.items()[menu.selected])
+    } else {
+        None
+    };
+
+    let chosen = match chosen {
+        Some(chosen) => chosen,
+        None => {
+            for (option, mut color) in option_query.iter_mut() {
+                *color = button_color(option.0 == menu.selected);
+            }
+            return;
+        }
+    };
+
+    // Whatever the menu turns into this frame shouldn't see the same press again
+    reset_pause_buttons(&mut keyboard_input, &gamepads, &mut gamepad_input);
+
+    match chosen {
+        PauseItem::Resume => {
+            state.pop().unwrap();
+            return;
+        }
+        PauseItem::QuitToMainMenu => {
+            state.replace(AppState::MainMenu).unwrap();
+            return;
+        }
+        PauseItem::Options => {
+            menu.page = PausePage::Options;
+            menu.selected = 0;
+        }
+        PauseItem::Back => {
+            menu.page = PausePage::Main;
+            menu.selected = 1;
+        }
+        PauseItem::Fullscreen => {
+            if let Some(window) = windows.get_primary_mut() {
+                let mode = if window.mode() == WindowMode::Windowed {
+                    WindowMode::BorderlessFullscreen
+                } else {
+                    WindowMode::Windowed
+                };
+                window.set_mode(mode);
+            }
+        }
+        PauseItem::VSync => {
+            if let Some(window) = windows.get_primary_mut() {
+                let vsync = !window.vsync();
+                window.set_vsync(vsync);
+            }
+        }
+    }
+
+    // Rebuild the page to show the new page or setting
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+    spawn_page(&mut commands, &font, &windows, &menu);
+}
+
+fn reset_pause_buttons(
+    keyboard_input: &mut Input<KeyCode>,
+    gamepads: &Gamepads,
+    gamepad_input: &mut Input<GamepadButton>,
+) {
+    keyboard_input.reset(KeyCode::Escape);
+    keyboard_input.reset(KeyCode::Return);
+    for gamepad in gamepads.iter() {
+        for button in [
+            GamepadButtonType::Start,
+            GamepadButtonType::South,
+            GamepadButtonType::East,
+        ] {
+            gamepad_input.reset(GamepadButton(*gamepad, button));
+        }
+    }
+}
+
+fn spawn_page(commands: &mut Commands, font: &UiFont, windows: &Windows, menu: &PauseMenu) {
+    spawn_panel(
+        commands,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        Color::rgb(0.08, 0.08, 0.1),
+        AlignItems::Stretch,
+        |panel| {
+            spawn_title(panel, menu.page.title(), text_style(font, 40.0));
+
+            for (index, item) in menu.page.items().iter().enumerate() {
+                spawn_button(
+                    panel,
+                    &item.label(windows),
+                    text_style(font, 28.0),
+                    index == menu.selected,
+                    PauseOption(index),
+                );
+            }
+        },
+    )
+    .insert(PauseScreen);
+}
@@ -0,0 +1,203 @@
+use bevy::{
+    app::Plugin,
+    math::Vec2,
+    prelude::{
+        App, BuildChildren, Color, Commands, Entity, EventReader, IntoChainSystem,
+        ParallelSystemDescriptorCoercion, Query, Res, ResMut, State, SystemSet, Transform, With,
+    },
+    sprite::{Sprite, SpriteBundle},
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    physics::{ColliderBundle, ColliderPositionSync, RapierConfiguration, RigidBodyBundle},
+    prelude::{
+        CoefficientCombineRule, ColliderMaterial, ColliderShape, RigidBodyDominance,
+        RigidBodyMassPropsFlags, RigidBodyVelocityComponent,
+    },
+};
+
+use crate::{
+    input::TickInput,
+    progression::{
+        collect_experience, drop_experience_gems, Experience, ExperienceGem, Level, LevelUp,
+        PickupRadius, XpCurve,
+    },
+    spatial::{index_entities, SpatialIndex, GEM_CELL_SIZE},
+    upgrade::{
+        choose_upgrade, clear_level_ups, despawn_level_up_screen, queue_level_ups, select_upgrade,
+        spawn_level_up_screen, PendingLevelUps, UpgradeChoices, UpgradePool,
+    },
+    weapon::{Weapon, WeaponKind},
+    AddGameplaySystem, AppState, Died, Facing, FixedTime, Health, MaxHealth, MoveSpeed, Player,
+    RunEntity, RunSystem, TickPhase,
+};
+
+/// The player: spawning them for every run, moving them with `TickInput` and ending the run when
+/// they die, along with the experience and upgrades they collect on the way
+pub struct PlayerPlugin;
+
+impl Plugin for PlayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingLevelUps>()
+            .init_resource::<UpgradeChoices>()
+            .init_resource::<UpgradePool>()
+            .init_resource::<XpCurve>()
+            .insert_resource(SpatialIndex::<ExperienceGem>::new(GEM_CELL_SIZE))
+            .add_event::<LevelUp>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(spawn_player)
+                    .with_system(clear_level_ups),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(queue_level_ups.label(RunSystem::QueueLevelUps)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::LevelUp).with_system(spawn_level_up_screen),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::LevelUp)
+                    .with_system(choose_upgrade.chain(select_upgrade)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(AppState::LevelUp).with_system(despawn_level_up_screen),
+            )
+            .add_gameplay_system(TickPhase::Input, index_entities::<ExperienceGem>)
+            .add_gameplay_system(TickPhase::Movement, player_movement)
+            .add_gameplay_system(TickPhase::Movement, collect_experience)
+            .add_gameplay_system(TickPhase::Cleanup, player_death)
+            .add_gameplay_system(TickPhase::Cleanup, drop_experience_gems);
+    }
+}
+
+/// Spawns the player at the origin with their starting weapon. The world around them is filled in
+/// by `stream_chunks` as they move through it.
+fn spawn_player(mut commands: Commands, rapier_config: Res<RapierConfiguration>) {
+    let player = spawn_player_body(&mut commands, &rapier_config, Vec2::ZERO);
+    commands.entity(player).with_children(|player| {
+        player.spawn().insert(Weapon::new(WeaponKind::Wand));
+    });
+}
+
+/// Spawns the player at `position`, without any weapons
+pub fn spawn_player_body(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    position: Vec2,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform::from_translation(position.extend(0.0)),
+            sprite: Sprite {
+                color: Color::rgb(0.5, 0.5, 1.0),
+                custom_size: Some(Vec2::new(50.0, 50.0)),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        // A dynamic body so obstacles block the player, dominant so monsters can't push it around
+        .insert_bundle(RigidBodyBundle {
+            mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+            dominance: RigidBodyDominance(1).into(),
+            ..RigidBodyBundle::default()
+        })
+        .insert_bundle(ColliderBundle {
+            position: (Vector2::new(position.x, position.y) / rapier_config.scale).into(),
+            shape: ColliderShape::cuboid(50.0 / 2.0, 50.0 / 2.0).into(),
+            material: ColliderMaterial {
+                friction: 0.0,
+                friction_combine_rule: CoefficientCombineRule::Min,
+                restitution: 0.0,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(ColliderPositionSync::Discrete)
+        .insert(Player)
+        .insert(RunEntity)
+        .insert(Health(100.0))
+        .insert(MaxHealth(100.0))
+        .insert(MoveSpeed(150.0))
+        .insert(Facing(Vec2::X))
+        .insert(Experience::default())
+        .insert(Level::default())
+        .insert(PickupRadius(100.0))
+        .id()
+}
+
+fn player_movement(
+    tick_input: Res<TickInput>,
+    mut player_transform_query: Query<
+        (&MoveSpeed, &mut Facing, &mut RigidBodyVelocityComponent),
+        With<Player>,
+    >,
+) {
+    let direction = tick_input.movement.direction();
+    let direction = Vector2::new(direction.x, direction.y);
+
+    for (move_speed, mut facing, mut rb_vels) in player_transform_query.iter_mut() {
+        rb_vels.linvel = direction * move_speed.0;
+
+        if direction != Vector2::zeros() {
+            facing.0 = Vec2::new(direction.x, direction.y);
+        }
+    }
+}
+
+fn player_death(
+    mut died_events: EventReader<Died>,
+    mut fixed_time: ResMut<FixedTime>,
+    mut state: ResMut<State<AppState>>,
+    player_query: Query<(), With<Player>>,
+) {
+    for died in died_events.iter() {
+        if player_query.get(died.entity).is_ok() {
+            state.set(AppState::GameOver).unwrap();
+
+            // The run ends on the tick the player died on
+            fixed_time.interrupt();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::Vec2, prelude::KeyCode};
+
+    use crate::{testing::TestApp, AppState, Damage, DamageCause, TICKS_PER_SECOND};
+
+    #[test]
+    fn held_keys_move_player() {
+        let mut app = TestApp::new();
+        let player = app.spawn_player(Vec2::ZERO);
+
+        app.press(KeyCode::D);
+        app.tick(TICKS_PER_SECOND / 5);
+
+        // 150 units per second for a fifth of a second
+        let position = app.position(player);
+        assert!(
+            position.abs_diff_eq(Vec2::new(30.0, 0.0), 0.1),
+            "player ended up at {}",
+            position
+        );
+    }
+
+    #[test]
+    fn player_death_ends_run() {
+        let mut app = TestApp::new();
+        let player = app.spawn_player(Vec2::ZERO);
+
+        app.send(Damage {
+            target: player,
+            amount: 100.0,
+            cause: DamageCause::Contact,
+        });
+        app.tick(1);
+        app.update();
+
+        assert_eq!(*app.state(), AppState::GameOver);
+    }
+}
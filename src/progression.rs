@@ -0,0 +1,167 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::{
+    asset::Assets,
+    math::{Quat, Vec3},
+    prelude::{
+        Color, Commands, Component, EventReader, EventWriter, Query, Res, ResMut, Transform, With,
+        Without,
+    },
+    sprite::{Sprite, SpriteBundle},
+};
+use rand::Rng;
+
+use crate::{
+    archetype::{MonsterArchetype, MonsterKind},
+    rng::RunRng,
+    spatial::SpatialIndex,
+    Died, FixedTime, Player, RunEntity,
+};
+
+/// Gems closer than this to the player are collected
+const COLLECT_DISTANCE: f32 = 20.0;
+
+/// Furthest a gem lands from where its monster died, so gems from a crowd don't stack up
+const GEM_SCATTER: f32 = 8.0;
+
+/// Units per second at which gems inside the pickup radius fly towards the player
+const GEM_SPEED: f32 = 300.0;
+
+#[derive(Component)]
+pub struct ExperienceGem {
+    pub xp: u32,
+}
+
+/// Experience gathered towards the next level
+#[derive(Component, Default)]
+pub struct Experience(pub u32);
+
+#[derive(Component)]
+pub struct Level(pub u32);
+
+impl Default for Level {
+    fn default() -> Self {
+        Self(1)
+    }
+}
+
+/// Distance at which experience gems start flying towards the player
+#[derive(Component)]
+pub struct PickupRadius(pub f32);
+
+/// Sent for every level the player gains, so gaining two levels at once sends two events
+pub struct LevelUp {
+    /// Level reached
+    pub level: u32,
+}
+
+/// Experience needed to advance from `level` to the next one:
+/// `base + linear * (level - 1) ^ exponent`
+pub struct XpCurve {
+    pub base: f32,
+    pub linear: f32,
+    pub exponent: f32,
+}
+
+impl Default for XpCurve {
+    fn default() -> Self {
+        Self {
+            base: 5.0,
+            linear: 10.0,
+            exponent: 1.3,
+        }
+    }
+}
+
+impl XpCurve {
+    pub fn xp_to_next_level(&self, level: u32) -> u32 {
+        let level = level.saturating_sub(1) as f32;
+        let xp = (self.base + self.linear * level.powf(self.exponent)).round() as u32;
+
+        // A free level would level up forever
+        xp.max(1)
+    }
+}
+
+pub fn drop_experience_gems(
+    mut commands: Commands,
+    mut died_events: EventReader<Died>,
+    archetypes: Res<Assets<MonsterArchetype>>,
+    mut run_rng: ResMut<RunRng>,
+    monster_query: Query<&MonsterKind>,
+) {
+    for died in died_events.iter() {
+        let archetype = match monster_query.get(died.entity) {
+            Ok(kind) => kind.archetype(&archetypes),
+            Err(_) => continue,
+        };
+
+        if archetype.xp == 0 {
+            continue;
+        }
+
+        let scatter = Vec3::new(
+            run_rng.drops.gen_range(-GEM_SCATTER..GEM_SCATTER),
+            run_rng.drops.gen_range(-GEM_SCATTER..GEM_SCATTER),
+            0.0,
+        );
+
+        commands
+            .spawn_bundle(SpriteBundle {
+                transform: Transform {
+                    translation: died.translation + scatter,
+                    rotation: Quat::from_rotation_z(FRAC_PI_4),
+                    scale: Vec3::new(8.0, 8.0, 0.0),
+                },
+                sprite: Sprite {
+                    color: Color::rgb(0.2, 0.6, 1.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(RunEntity)
+            .insert(ExperienceGem { xp: archetype.xp });
+    }
+}
+
+pub fn collect_experience(
+    mut commands: Commands,
+    mut fixed_time: ResMut<FixedTime>,
+    xp_curve: Res<XpCurve>,
+    gem_index: Res<SpatialIndex<ExperienceGem>>,
+    mut level_up_events: EventWriter<LevelUp>,
+    mut player_query: Query<(&Transform, &PickupRadius, &mut Experience, &mut Level), With<Player>>,
+    mut gem_query: Query<(&mut Transform, &ExperienceGem), Without<Player>>,
+) {
+    let (player_transform, pickup_radius, mut experience, mut level) = player_query.single_mut();
+    let player_translation = player_transform.translation.truncate();
+
+    let nearby = gem_index.within_radius(player_translation, pickup_radius.0.max(COLLECT_DISTANCE));
+    for (gem_entity, _) in nearby {
+        let (mut gem_transform, gem) = match gem_query.get_mut(gem_entity) {
+            Ok(gem) => gem,
+            Err(_) => continue,
+        };
+
+        let offset = player_translation - gem_transform.translation.truncate();
+        let distance = offset.length();
+
+        if distance <= COLLECT_DISTANCE {
+            commands.entity(gem_entity).despawn();
+            experience.0 += gem.xp;
+        } else if distance <= pickup_radius.0 {
+            let step = (GEM_SPEED * fixed_time.delta_seconds()).min(distance);
+            let step = offset / distance * step;
+            gem_transform.translation += step.extend(0.0);
+        }
+    }
+
+    while experience.0 >= xp_curve.xp_to_next_level(level.0) {
+        experience.0 -= xp_curve.xp_to_next_level(level.0);
+        level.0 += 1;
+        level_up_events.send(LevelUp { level: level.0 });
+
+        // The level up screen has to come up before the next tick, whatever the frame rate
+        fixed_time.interrupt();
+    }
+}
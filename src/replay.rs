@@ -0,0 +1,261 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{
+    app::AppExit,
+    log::{error, info},
+    math::Vec2,
+    prelude::{EventWriter, Or, Query, ResMut, Transform, With},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{Movement, TickInput},
+    Monster, Player,
+};
+
+/// Everything needed to play a run back tick for tick. Saved as RON.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Replay {
+    seed: u64,
+    ticks: u32,
+    /// Movement of every tick, run-length encoded as the movement and how many ticks it lasted
+    movement: Vec<(u8, u32)>,
+    /// View half extents along with the tick they changed on
+    views: Vec<(u32, Option<(f32, f32)>)>,
+    /// Index of the upgrade picked on every level up, in order
+    upgrades: Vec<usize>,
+    /// `world_checksum` after the last tick
+    checksum: u64,
+}
+
+/// Records the current run, saving it to `path` once the run ends
+pub struct Recorder {
+    path: PathBuf,
+    replay: Replay,
+    last_view: Option<Option<Vec2>>,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            replay: Replay::default(),
+            last_view: None,
+        }
+    }
+
+    /// Throws away whatever was recorded and starts recording a run with the given seed
+    pub fn start(&mut self, seed: u64) {
+        self.replay = Replay {
+            seed,
+            ..Replay::default()
+        };
+        self.last_view = None;
+    }
+
+    pub fn record_tick(&mut self, input: &TickInput) {
+        let tick = self.replay.ticks;
+        self.replay.ticks += 1;
+
+        match self.replay.movement.last_mut() {
+            Some((movement, ticks)) if *movement == input.movement.0 => *ticks += 1,
+            _ => self.replay.movement.push((input.movement.0, 1)),
+        }
+
+        if self.last_view != Some(input.view_half_extents) {
+            let view = input.view_half_extents.map(|view| (view.x, view.y));
+            self.replay.views.push((tick, view));
+            self.last_view = Some(input.view_half_extents);
+        }
+    }
+
+    pub fn record_upgrade(&mut self, index: usize) {
+        self.replay.upgrades.push(index);
+    }
+
+    fn save(&mut self, checksum: u64) {
+        self.replay.checksum = checksum;
+
+        let result = ron::ser::to_string(&self.replay)
+            .map_err(anyhow::Error::from)
+            .and_then(|replay| fs::write(&self.path, replay).map_err(anyhow::Error::from));
+        match result {
+            Ok(()) => info!(
+                "saved replay of {} ticks to {}",
+                self.replay.ticks,
+                self.path.display()
+            ),
+            Err(err) => error!("couldn't save replay to {}: {}", self.path.display(), err),
+        }
+
+        // Nothing left to save until the next run starts
+        self.replay.ticks = 0;
+    }
+}
+
+/// Feeds a recorded run back into the simulation in place of real input
+pub struct Playback {
+    replay: Replay,
+    tick: u32,
+    /// Position in `replay.movement`, as the run and how many of its ticks were used
+    movement: (usize, u32),
+    view: usize,
+    upgrade: usize,
+    checked: bool,
+}
+
+impl Playback {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let replay = ron::de::from_bytes(&fs::read(path)?)?;
+        Ok(Self {
+            replay,
+            tick: 0,
+            movement: (0, 0),
+            view: 0,
+            upgrade: 0,
+            checked: false,
+        })
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.replay.seed
+    }
+
+    /// Whether every recorded tick has been played back
+    pub fn is_finished(&self) -> bool {
+        self.tick >= self.replay.ticks
+    }
+
+    pub fn next_tick(&mut self) -> TickInput {
+        let (run, used) = &mut self.movement;
+        let movement = match self.replay.movement.get(*run) {
+            Some((movement, ticks)) => {
+                *used += 1;
+                if *used == *ticks {
+                    *run += 1;
+                    *used = 0;
+                }
+                Movement(*movement)
+            }
+            None => Movement::default(),
+        };
+
+        while let Some((tick, _)) = self.replay.views.get(self.view + 1) {
+            if *tick > self.tick {
+                break;
+            }
+            self.view += 1;
+        }
+        let view_half_extents = self
+            .replay
+            .views
+            .get(self.view)
+            .and_then(|(_, view)| *view)
+            .map(|(x, y)| Vec2::new(x, y));
+
+        self.tick += 1;
+        TickInput {
+            movement,
+            view_half_extents,
+        }
+    }
+
+    pub fn next_upgrade(&mut self) -> Option<usize> {
+        let upgrade = self.replay.upgrades.get(self.upgrade).copied();
+        self.upgrade += 1;
+        upgrade
+    }
+}
+
+/// Saves the recording when a run ends, by dying or by quitting to the main menu
+pub fn save_replay(
+    recorder: Option<ResMut<Recorder>>,
+    body_query: Query<&Transform, Or<(With<Player>, With<Monster>)>>,
+) {
+    if let Some(mut recorder) = recorder {
+        if recorder.replay.ticks > 0 {
+            recorder.save(world_checksum(body_query.iter()));
+        }
+    }
+}
+
+/// Compares the world against the recording once all of it was played back, then quits. A
+/// diverged replay exits with a failure status, so scripts playing replays back can check on it.
+pub fn finish_replay(
+    playback: Option<ResMut<Playback>>,
+    mut app_exit_events: EventWriter<AppExit>,
+    body_query: Query<&Transform, Or<(With<Player>, With<Monster>)>>,
+) {
+    let mut playback = match playback {
+        Some(playback) if playback.is_finished() && !playback.checked => playback,
+        _ => return,
+    };
+    playback.checked = true;
+
+    let ticks = playback.replay.ticks;
+    if world_checksum(body_query.iter()) == playback.replay.checksum {
+        info!("replay of {} ticks matched the recording", ticks);
+    } else {
+        error!("replay diverged from the recording after {} ticks", ticks);
+        // The window's event loop never hands control back to `main` to report it from there
+        std::process::exit(1);
+    }
+    app_exit_events.send(AppExit);
+}
+
+/// Hash of where the player and every monster are, independent of the order they're visited in
+fn world_checksum<'a>(transforms: impl Iterator<Item = &'a Transform>) -> u64 {
+    let mut positions = transforms
+        .map(|transform| {
+            (
+                transform.translation.x.to_bits(),
+                transform.translation.y.to_bits(),
+            )
+        })
+        .collect::<Vec<_>>();
+    positions.sort_unstable();
+
+    // FNV-1a
+    let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+    for (x, y) in positions {
+        for byte in x.to_le_bytes().into_iter().chain(y.to_le_bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        math::Vec2,
+        prelude::{KeyCode, Or, With},
+    };
+
+    use super::world_checksum;
+    use crate::{testing::TestApp, Monster, Player, TICKS_PER_SECOND};
+
+    /// Checksum after the player ran from a couple of zombies for two seconds, simulated
+    /// `ticks_per_frame` ticks at a time
+    fn chase_checksum(ticks_per_frame: u32) -> u64 {
+        let mut app = TestApp::new();
+        app.spawn_player(Vec2::ZERO);
+        app.spawn_monster("Zombie", Vec2::new(200.0, 0.0));
+        app.spawn_monster("Zombie", Vec2::new(-150.0, 100.0));
+
+        app.press(KeyCode::W);
+        app.tick_frames(2 * TICKS_PER_SECOND / ticks_per_frame, ticks_per_frame);
+
+        let transforms = app.transforms::<Or<(With<Player>, With<Monster>)>>();
+        world_checksum(transforms.iter())
+    }
+
+    #[test]
+    fn same_seed_gives_same_checksum_however_many_ticks_a_frame_runs() {
+        assert_eq!(chase_checksum(1), chase_checksum(2));
+    }
+}
@@ -0,0 +1,45 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Randomness for a single run, all derived from one seed so a run can be reproduced from it.
+/// Every use gets a separate stream, so e.g. drawing more numbers for spawning doesn't change
+/// which upgrades are offered.
+pub struct RunRng {
+    seed: u64,
+    pub spawning: ChaCha8Rng,
+    pub targeting: ChaCha8Rng,
+    pub drops: ChaCha8Rng,
+    pub upgrades: ChaCha8Rng,
+}
+
+impl RunRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            spawning: stream(seed, 0),
+            targeting: stream(seed, 1),
+            drops: stream(seed, 2),
+            upgrades: stream(seed, 3),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for RunRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Stream used for generating the world, which is seeded per chunk rather than once per run
+pub const WORLD_STREAM: u64 = 4;
+
+/// Independent stream `stream` of the generator seeded with `seed`
+pub fn stream(seed: u64, stream: u64) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    rng.set_stream(stream);
+    rng
+}
@@ -0,0 +1,385 @@
+use std::{marker::PhantomData, ops::Deref};
+
+use bevy::{
+    math::Vec2,
+    prelude::{Component, Entity, Query, ResMut, Transform, With},
+    utils::HashMap,
+};
+use ordered_float::OrderedFloat;
+
+/// Side length of a monster grid cell, roughly the size of the larger monsters
+pub const MONSTER_CELL_SIZE: f32 = 64.0;
+
+/// Side length of an experience gem grid cell, about a default pickup radius
+pub const GEM_CELL_SIZE: f32 = 128.0;
+
+/// Uniform grid bucketing entities by position, for neighbour queries that don't have to look
+/// at every entity
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<(Entity, Vec2)>>,
+    /// Inclusive bounds of the occupied cells, limiting how far searches have to look
+    min_cell: (i32, i32),
+    max_cell: (i32, i32),
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            min_cell: (i32::MAX, i32::MAX),
+            max_cell: (i32::MIN, i32::MIN),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        // Buckets in use are kept so rebuilding every tick doesn't reallocate them. Ones left empty
+        // since the last rebuild are dropped, or the map would grow with every cell ever visited.
+        self.cells.retain(|_, bucket| {
+            let occupied = !bucket.is_empty();
+            bucket.clear();
+            occupied
+        });
+        self.min_cell = (i32::MAX, i32::MAX);
+        self.max_cell = (i32::MIN, i32::MIN);
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = self.cell(position);
+        self.min_cell = (self.min_cell.0.min(cell.0), self.min_cell.1.min(cell.1));
+        self.max_cell = (self.max_cell.0.max(cell.0), self.max_cell.1.max(cell.1));
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+
+    fn is_empty(&self) -> bool {
+        self.min_cell.0 > self.max_cell.0
+    }
+
+    /// Up to `k` entities closest to `point`, closest first
+    pub fn nearest(&self, point: Vec2, k: usize) -> Vec<(Entity, Vec2)> {
+        let mut found = Vec::new();
+        if k == 0 || self.is_empty() {
+            return found;
+        }
+
+        let center = self.cell(point);
+        let max_ring = [
+            center.0 - self.min_cell.0,
+            self.max_cell.0 - center.0,
+            center.1 - self.min_cell.1,
+            self.max_cell.1 - center.1,
+        ]
+        .into_iter()
+        .max()
+        .unwrap()
+        .max(0);
+
+        for ring in 0..=max_ring {
+            for cell in ring_cells(center, ring) {
+                if let Some(bucket) = self.cells.get(&cell) {
+                    found.extend(bucket.iter().copied());
+                }
+            }
+
+            // Everything in the next ring is at least `ring` whole cells away
+            if found.len() >= k {
+                found.sort_by_key(|(_, position)| OrderedFloat(position.distance_squared(point)));
+                found.truncate(k);
+
+                let reach = ring as f32 * self.cell_size;
+                if found[k - 1].1.distance_squared(point) <= reach * reach {
+                    return found;
+                }
+            }
+        }
+
+        found.sort_by_key(|(_, position)| OrderedFloat(position.distance_squared(point)));
+        found.truncate(k);
+        found
+    }
+
+    /// Every entity within `radius` of `point`, in no particular order
+    pub fn within_radius(
+        &self,
+        point: Vec2,
+        radius: f32,
+    ) -> impl Iterator<Item = (Entity, Vec2)> + '_ {
+        let min = self.cell(point - Vec2::splat(radius));
+        let max = self.cell(point + Vec2::splat(radius));
+
+        // Cells outside the occupied bounds are known to be empty
+        let (min_x, max_x) = (min.0.max(self.min_cell.0), max.0.min(self.max_cell.0));
+        let (min_y, max_y) = (min.1.max(self.min_cell.1), max.1.min(self.max_cell.1));
+
+        let radius_squared = radius * radius;
+        (min_x..=max_x)
+            .flat_map(move |x| (min_y..=max_y).map(move |y| (x, y)))
+            .filter_map(move |cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .filter(move |(_, position)| position.distance_squared(point) <= radius_squared)
+    }
+
+    fn cell(&self, position: Vec2) -> (i32, i32) {
+        let cell = (position / self.cell_size).floor();
+        (cell.x as i32, cell.y as i32)
+    }
+}
+
+/// Cells on the border of the square `ring` cells out from `center`
+fn ring_cells(center: (i32, i32), ring: i32) -> impl Iterator<Item = (i32, i32)> {
+    (-ring..=ring).flat_map(move |x| {
+        (-ring..=ring)
+            .filter(move |y| x.abs() == ring || y.abs() == ring)
+            .map(move |y| (center.0 + x, center.1 + y))
+    })
+}
+
+/// Rows and columns of cells laid over the world around a point, for showing what is where
+/// around it. Rows count down from the top, like on a screen.
+#[derive(Clone, Copy)]
+pub struct CellGrid {
+    pub cell_size: Vec2,
+    /// Where the point the grid is laid around lands, in cells from the top left corner
+    pub origin: Vec2,
+    pub columns: i32,
+    pub rows: i32,
+}
+
+impl CellGrid {
+    /// Column and row of the cell containing `offset` from the grid's point, which may lie
+    /// outside of the grid
+    pub fn cell_coordinates(&self, offset: Vec2) -> (i32, i32) {
+        let column = offset.x / self.cell_size.x + self.origin.x;
+        let row = -offset.y / self.cell_size.y + self.origin.y;
+        (column.floor() as i32, row.floor() as i32)
+    }
+
+    /// Index of the cell containing `offset` from the grid's point into the cells stored row by
+    /// row, if it lies inside of the grid
+    pub fn cell(&self, offset: Vec2) -> Option<usize> {
+        let (column, row) = self.cell_coordinates(offset);
+        let inside = (0..self.columns).contains(&column) && (0..self.rows).contains(&row);
+        inside.then(|| (row * self.columns + column) as usize)
+    }
+
+    /// Indices of every cell of the grid laid around `center` that `obstacle` overlaps. Obstacles
+    /// are scaled up to their size, so they can cover several cells.
+    pub fn obstacle_cells(
+        &self,
+        center: Vec2,
+        obstacle: &Transform,
+    ) -> impl Iterator<Item = usize> {
+        let offset = obstacle.translation.truncate() - center;
+        let half_extents = obstacle.scale.truncate() / 2.0;
+        let (min_column, max_row) = self.cell_coordinates(offset - half_extents);
+        let (max_column, min_row) = self.cell_coordinates(offset + half_extents);
+
+        let (columns, rows) = (self.columns, self.rows);
+        (min_row.max(0)..=max_row.min(rows - 1)).flat_map(move |row| {
+            (min_column.max(0)..=max_column.min(columns - 1))
+                .map(move |column| (row * columns + column) as usize)
+        })
+    }
+}
+
+/// Positions of every entity with the component `T`, rebuilt at the start of each tick by
+/// `index_entities::<T>`
+pub struct SpatialIndex<T> {
+    grid: SpatialGrid,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> SpatialIndex<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            grid: SpatialGrid::new(cell_size),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Deref for SpatialIndex<T> {
+    type Target = SpatialGrid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grid
+    }
+}
+
+pub fn index_entities<T: Component>(
+    mut index: ResMut<SpatialIndex<T>>,
+    query: Query<(Entity, &Transform), With<T>>,
+) {
+    index.grid.clear();
+    for (entity, transform) in query.iter() {
+        index.grid.insert(entity, transform.translation.truncate());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{math::Vec2, prelude::Entity};
+
+    use super::SpatialGrid;
+
+    fn grid(positions: &[Vec2]) -> SpatialGrid {
+        let mut grid = SpatialGrid::new(64.0);
+        for (id, position) in positions.iter().enumerate() {
+            grid.insert(Entity::from_raw(id as u32), *position);
+        }
+        grid
+    }
+
+    fn ids(found: impl IntoIterator<Item = (Entity, Vec2)>) -> Vec<u32> {
+        found.into_iter().map(|(entity, _)| entity.id()).collect()
+    }
+
+    #[test]
+    fn empty_grid_finds_nothing() {
+        let mut grid = grid(&[Vec2::new(10.0, 10.0)]);
+        grid.clear();
+
+        assert!(grid.nearest(Vec2::ZERO, 3).is_empty());
+        assert_eq!(grid.within_radius(Vec2::ZERO, 1000.0).count(), 0);
+    }
+
+    #[test]
+    fn clear_drops_buckets_left_empty() {
+        let mut grid = grid(&[Vec2::ZERO, Vec2::new(1000.0, 0.0)]);
+        grid.clear();
+        grid.insert(Entity::from_raw(0), Vec2::ZERO);
+        grid.clear();
+
+        assert_eq!(grid.cells.len(), 1);
+    }
+
+    #[test]
+    fn nearest_looks_past_the_cell_of_the_point() {
+        // The point is at the edge of its cell, closer to the entity in the next cell over
+        let grid = grid(&[
+            Vec2::new(1.0, 0.0),
+            Vec2::new(70.0, 0.0),
+            Vec2::new(-1.0, 0.0),
+        ]);
+
+        assert_eq!(ids(grid.nearest(Vec2::new(63.0, 0.0), 1)), [1]);
+        assert_eq!(ids(grid.nearest(Vec2::new(-63.0, 0.0), 1)), [2]);
+    }
+
+    #[test]
+    fn nearest_returns_everything_when_asked_for_more() {
+        let grid = grid(&[
+            Vec2::new(300.0, 0.0),
+            Vec2::new(0.0, 64.0),
+            Vec2::new(-64.0, -64.0),
+        ]);
+
+        assert_eq!(ids(grid.nearest(Vec2::ZERO, 5)), [1, 2, 0]);
+    }
+
+    #[test]
+    fn within_radius_includes_the_edge_of_the_circle() {
+        let grid = grid(&[
+            Vec2::new(64.0, 0.0),
+            Vec2::new(0.0, -64.0),
+            Vec2::new(64.1, 0.0),
+            Vec2::new(46.0, 46.0),
+        ]);
+
+        let mut found = ids(grid.within_radius(Vec2::ZERO, 64.0));
+        found.sort_unstable();
+        assert_eq!(found, [0, 1]);
+    }
+}
+
+#[cfg(all(test, feature = "bench"))]
+mod benches {
+    extern crate test;
+
+    use bevy::{math::Vec2, prelude::Entity};
+    use ordered_float::OrderedFloat;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use test::{black_box, Bencher};
+
+    use super::SpatialGrid;
+
+    const MONSTERS: u32 = 5000;
+
+    /// Monsters spread over an area a few screens wide, as in a long run
+    fn monsters() -> Vec<(Entity, Vec2)> {
+        let mut rng = StdRng::seed_from_u64(0);
+        (0..MONSTERS)
+            .map(|id| {
+                let position = Vec2::new(
+                    rng.gen_range(-2000.0..2000.0),
+                    rng.gen_range(-2000.0..2000.0),
+                );
+                (Entity::from_raw(id), position)
+            })
+            .collect()
+    }
+
+    fn grid(monsters: &[(Entity, Vec2)]) -> SpatialGrid {
+        let mut grid = SpatialGrid::new(64.0);
+        for (entity, position) in monsters {
+            grid.insert(*entity, *position);
+        }
+        grid
+    }
+
+    #[bench]
+    fn rebuild_5000(b: &mut Bencher) {
+        let monsters = monsters();
+        let mut grid = grid(&monsters);
+
+        b.iter(|| {
+            grid.clear();
+            for (entity, position) in &monsters {
+                grid.insert(*entity, *position);
+            }
+        });
+    }
+
+    #[bench]
+    fn nearest_5000(b: &mut Bencher) {
+        let grid = grid(&monsters());
+        b.iter(|| grid.nearest(black_box(Vec2::new(10.0, -20.0)), 3));
+    }
+
+    #[bench]
+    fn nearest_linear_scan_5000(b: &mut Bencher) {
+        let monsters = monsters();
+        let point = Vec2::new(10.0, -20.0);
+
+        b.iter(|| {
+            let mut sorted = monsters.clone();
+            sorted.sort_by_key(|(_, position)| OrderedFloat(position.distance_squared(point)));
+            sorted.truncate(3);
+            sorted
+        });
+    }
+
+    #[bench]
+    fn within_radius_5000(b: &mut Bencher) {
+        let grid = grid(&monsters());
+        b.iter(|| {
+            grid.within_radius(black_box(Vec2::new(10.0, -20.0)), 150.0)
+                .count()
+        });
+    }
+
+    #[bench]
+    fn within_radius_linear_scan_5000(b: &mut Bencher) {
+        let monsters = monsters();
+        let point = Vec2::new(10.0, -20.0);
+
+        b.iter(|| {
+            monsters
+                .iter()
+                .filter(|(_, position)| position.distance_squared(point) <= 150.0 * 150.0)
+                .count()
+        });
+    }
+}
@@ -0,0 +1,227 @@
+use std::{f32::consts::TAU, time::Duration};
+
+use bevy::{
+    asset::{AssetServer, Assets, Handle},
+    core::Timer,
+    math::{Vec2, Vec3},
+    prelude::{Commands, Entity, Query, Res, ResMut, Transform, With},
+    sprite::{Sprite, SpriteBundle},
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    physics::{ColliderBundle, ColliderPositionSync, RapierConfiguration, RigidBodyBundle},
+    prelude::{
+        CoefficientCombineRule, ColliderMassProps, ColliderMaterial, ColliderShape,
+        RigidBodyMassPropsFlags,
+    },
+};
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    archetype::{MonsterArchetype, MonsterArchetypes, MonsterKind},
+    input::TickInput,
+    rng::RunRng,
+    FixedTime, Health, Monster, Obstacle, Player, RunEntity, RunTime,
+};
+
+/// Distance beyond the corners of the camera view at which monsters appear
+const SPAWN_MARGIN: f32 = 50.0;
+
+/// Spawn ring radius used when there is no window to measure the view from
+const FALLBACK_SPAWN_RADIUS: f32 = 700.0;
+
+/// How many positions on the ring are tried before a monster is skipped for this wave
+const SPAWN_ATTEMPTS: usize = 8;
+
+/// Spawns waves of monsters around the player. Waves grow larger and come more often the longer
+/// the run lasts.
+pub struct MonsterSpawner {
+    next_wave: Timer,
+    pub max_monsters: usize,
+    pub base_interval: f32,
+    pub min_interval: f32,
+    /// Seconds shaved off the wave interval per minute survived
+    pub interval_decay: f32,
+    pub base_wave_size: usize,
+    /// Seconds survived for each extra monster per wave
+    pub wave_growth: f32,
+}
+
+impl Default for MonsterSpawner {
+    fn default() -> Self {
+        Self {
+            // Finished right away so the first wave arrives on the first tick
+            next_wave: Timer::from_seconds(0.0, false),
+            max_monsters: 300,
+            base_interval: 3.0,
+            min_interval: 0.5,
+            interval_decay: 0.5,
+            base_wave_size: 2,
+            wave_growth: 20.0,
+        }
+    }
+}
+
+impl MonsterSpawner {
+    /// Gets ready for a new run, sending the first wave right away again
+    pub fn reset(&mut self) {
+        self.next_wave = Timer::from_seconds(0.0, false);
+    }
+
+    fn wave_interval(&self, elapsed: Duration) -> f32 {
+        let minutes = elapsed.as_secs_f32() / 60.0;
+        (self.base_interval - minutes * self.interval_decay).max(self.min_interval)
+    }
+
+    fn wave_size(&self, elapsed: Duration) -> usize {
+        self.base_wave_size + (elapsed.as_secs_f32() / self.wave_growth) as usize
+    }
+}
+
+pub fn reset_spawner(mut spawner: ResMut<MonsterSpawner>) {
+    spawner.reset();
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn spawn_monsters(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    run_time: Res<RunTime>,
+    rapier_config: Res<RapierConfiguration>,
+    tick_input: Res<TickInput>,
+    asset_server: Res<AssetServer>,
+    archetype_assets: Res<Assets<MonsterArchetype>>,
+    monster_archetypes: Res<MonsterArchetypes>,
+    mut spawner: ResMut<MonsterSpawner>,
+    mut run_rng: ResMut<RunRng>,
+    player_query: Query<&Transform, With<Player>>,
+    monster_query: Query<(), With<Monster>>,
+    obstacle_query: Query<&Transform, With<Obstacle>>,
+) {
+    if !spawner.next_wave.tick(fixed_time.step).finished() {
+        return;
+    }
+
+    let elapsed = run_time.0.as_secs_f32();
+    let archetypes = monster_archetypes
+        .0
+        .iter()
+        .filter_map(|handle| Some((handle, archetype_assets.get(handle)?)))
+        .filter(|(_, archetype)| archetype.spawn_after <= elapsed)
+        .collect::<Vec<_>>();
+
+    // Archetypes are still loading, try again next tick
+    if archetypes.is_empty() {
+        return;
+    }
+
+    let interval = spawner.wave_interval(run_time.0);
+    spawner.next_wave = Timer::from_seconds(interval, false);
+
+    let alive = monster_query.iter().count();
+    let count = spawner
+        .wave_size(run_time.0)
+        .min(spawner.max_monsters.saturating_sub(alive));
+    if count == 0 {
+        return;
+    }
+
+    let center = player_query.single().translation.truncate();
+    let radius = spawn_radius(&tick_input);
+
+    let rng = &mut run_rng.spawning;
+    for _ in 0..count {
+        let (handle, archetype) =
+            match archetypes.choose_weighted(rng, |(_, archetype)| archetype.spawn_weight) {
+                Ok(choice) => *choice,
+                Err(_) => return,
+            };
+
+        let position = (0..SPAWN_ATTEMPTS)
+            .map(|_| {
+                let angle = rng.gen_range(0.0..TAU);
+                center + Vec2::new(angle.cos(), angle.sin()) * radius
+            })
+            .find(|position| {
+                !obstacle_query
+                    .iter()
+                    .any(|obstacle| overlaps_obstacle(*position, archetype.size, obstacle))
+            });
+
+        if let Some(position) = position {
+            spawn_monster(
+                &mut commands,
+                &asset_server,
+                &rapier_config,
+                handle,
+                archetype,
+                position,
+            );
+        }
+    }
+}
+
+/// Distance from the player at which monsters appear, just beyond the corners of the view
+pub fn spawn_radius(tick_input: &TickInput) -> f32 {
+    tick_input
+        .view_half_extents
+        .map_or(FALLBACK_SPAWN_RADIUS, |half_extents| {
+            half_extents.length() + SPAWN_MARGIN
+        })
+}
+
+fn overlaps_obstacle(position: Vec2, size: f32, obstacle: &Transform) -> bool {
+    let reach = (obstacle.scale.truncate() + Vec2::splat(size)) / 2.0;
+    let offset = (position - obstacle.translation.truncate()).abs();
+    offset.x < reach.x && offset.y < reach.y
+}
+
+pub fn spawn_monster(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    rapier_config: &RapierConfiguration,
+    handle: &Handle<MonsterArchetype>,
+    archetype: &MonsterArchetype,
+    position: Vec2,
+) -> Entity {
+    let size = archetype.size / rapier_config.scale;
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform::from_translation(Vec3::new(position.x, position.y, 0.0)),
+            sprite: Sprite {
+                color: archetype.color(),
+                custom_size: Some(Vec2::new(archetype.size, archetype.size)),
+                ..Default::default()
+            },
+            texture: archetype
+                .sprite
+                .as_ref()
+                .map(|path| asset_server.load(path.as_str()))
+                .unwrap_or_default(),
+            ..Default::default()
+        })
+        .insert_bundle(RigidBodyBundle {
+            mass_properties: RigidBodyMassPropsFlags::ROTATION_LOCKED.into(),
+            ..RigidBodyBundle::default()
+        })
+        .insert_bundle(ColliderBundle {
+            position: (Vector2::new(position.x, position.y) / rapier_config.scale).into(),
+            shape: ColliderShape::cuboid(size / 2.0, size / 2.0).into(),
+            mass_properties: ColliderMassProps::Density(archetype.mass / (size * size)).into(),
+            material: ColliderMaterial {
+                friction: 0.0,
+                friction_combine_rule: CoefficientCombineRule::Min,
+                restitution: 1.0,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(ColliderPositionSync::Discrete)
+        .insert(Monster)
+        .insert(RunEntity)
+        .insert(MonsterKind(handle.clone()))
+        .insert(Health(archetype.health))
+        .id()
+}
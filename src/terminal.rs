@@ -0,0 +1,498 @@
+use std::{
+    io::{stdout, Write},
+    time::Duration,
+};
+
+use bevy::{
+    app::{AppExit, Plugin},
+    asset::Assets,
+    core::Time,
+    input::{keyboard::KeyboardInput, ElementState, Input},
+    math::Vec2,
+    prelude::{
+        App, Color, CoreStage, EventWriter, KeyCode, ParallelSystemDescriptorCoercion, Query, Res,
+        ResMut, State, SystemLabel, SystemSet, Transform, With,
+    },
+    sprite::Sprite,
+    utils::HashMap,
+};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyModifiers},
+    execute, queue,
+    style::{self, Print, ResetColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+use crate::{
+    accumulate_frame_time,
+    archetype::{MonsterArchetype, MonsterKind},
+    end_run,
+    input::{keyboard_movement, TickInput},
+    menu::{game_over_input, main_menu_input},
+    pause::pause_input,
+    progression::{ExperienceGem, Level},
+    rng::RunRng,
+    spatial::CellGrid,
+    upgrade::UpgradeChoices,
+    AddGameplaySystem, AppState, Health, KillCount, MaxHealth, Monster, Obstacle, Player,
+    Projectile, RunSystem, RunTime, TickPhase,
+};
+
+/// Terminals are redrawn about this often, which is plenty over a slow connection. Meant for the
+/// `ScheduleRunnerSettings` of apps with `TerminalPlugin`.
+pub const TERMINAL_FRAME_TIME: Duration = Duration::from_millis(33);
+
+/// Size of the part of the world a character covers. Characters are about twice as tall as they
+/// are wide, so the world doesn't look squashed.
+const CELL_SIZE: (f32, f32) = (12.0, 24.0);
+
+/// Terminals only report key presses, repeating them while a key is held. Movement keys count as
+/// held for this long after the last press, which covers the delay before the repeats start.
+const MOVEMENT_HOLD: f64 = 0.5;
+
+const MOVEMENT_KEYS: [KeyCode; 8] = [
+    KeyCode::W,
+    KeyCode::A,
+    KeyCode::S,
+    KeyCode::D,
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+];
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+enum TerminalSystem {
+    /// Fills the screen buffer with the world
+    DrawWorld,
+    /// Writes menus and the status line over the world
+    DrawOverlay,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    glyph: char,
+    color: style::Color,
+}
+
+const BLANK: Cell = Cell {
+    glyph: ' ',
+    color: style::Color::Reset,
+};
+
+/// Characters to show in the terminal, with whatever was shown last so only the cells that
+/// changed have to be written out again
+#[derive(Default)]
+struct TerminalScreen {
+    /// Columns and rows
+    size: (u16, u16),
+    cells: Vec<Cell>,
+    drawn: Vec<Cell>,
+}
+
+impl TerminalScreen {
+    /// Rows showing the world, above the status line
+    fn world_rows(&self) -> u16 {
+        self.size.1.saturating_sub(1)
+    }
+
+    /// Half the size of the part of the world the screen shows
+    fn view_half_extents(&self) -> Vec2 {
+        Vec2::new(
+            self.size.0 as f32 * CELL_SIZE.0,
+            self.world_rows() as f32 * CELL_SIZE.1,
+        ) / 2.0
+    }
+
+    /// Cells showing the world, with the center of the view in the middle
+    fn grid(&self) -> CellGrid {
+        CellGrid {
+            cell_size: Vec2::new(CELL_SIZE.0, CELL_SIZE.1),
+            origin: Vec2::new(self.size.0 as f32, self.world_rows() as f32) / 2.0,
+            columns: self.size.0 as i32,
+            rows: self.world_rows() as i32,
+        }
+    }
+
+    /// Shows `cell` at `offset` from the center of the view, unless that is off screen
+    fn put(&mut self, offset: Vec2, cell: Cell) {
+        if let Some(index) = self.grid().cell(offset) {
+            self.cells[index] = cell;
+        }
+    }
+
+    /// Writes `text` centered on `row`, which may also be the status line
+    fn text(&mut self, row: u16, text: &str) {
+        if row >= self.size.1 {
+            return;
+        }
+
+        let columns = self.size.0 as usize;
+        let start = row as usize * columns + columns.saturating_sub(text.chars().count()) / 2;
+        for (offset, glyph) in text.chars().take(columns).enumerate() {
+            self.cells[start + offset] = Cell {
+                glyph,
+                color: style::Color::White,
+            };
+        }
+    }
+}
+
+/// Keys held down according to the terminal, along with when they count as released
+#[derive(Default)]
+struct HeldKeys(HashMap<KeyCode, f64>);
+
+/// Puts the terminal in raw mode on an alternate screen for as long as it lives
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn enter() -> crossterm::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // Nothing left to do about the terminal if it can't be restored
+        let _ = execute!(stdout(), ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Plays the game in the terminal on top of `VampsPlugin`, drawing the world as characters and
+/// reading keys from the terminal rather than from a window. Builds on `HeadlessPlugins` in place
+/// of `DefaultPlugins`, and needs the terminal set up by a `TerminalGuard` while the app runs.
+pub struct TerminalPlugin;
+
+impl Plugin for TerminalPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HeldKeys>()
+            .init_resource::<TerminalScreen>()
+            .add_gameplay_system(TickPhase::Input, sample_terminal_input)
+            .add_system_to_stage(CoreStage::First, read_terminal_keys)
+            .add_system_to_stage(CoreStage::PreUpdate, accumulate_frame_time)
+            .add_system_set(SystemSet::on_enter(AppState::MainMenu).with_system(end_run))
+            .add_system_set(SystemSet::on_update(AppState::MainMenu).with_system(main_menu_input))
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(pause_input.after(RunSystem::QueueLevelUps)),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(paused_input))
+            .add_system_set(SystemSet::on_update(AppState::GameOver).with_system(game_over_input))
+            .add_system_to_stage(CoreStage::Last, draw_world.label(TerminalSystem::DrawWorld))
+            .add_system_to_stage(
+                CoreStage::Last,
+                draw_overlay
+                    .label(TerminalSystem::DrawOverlay)
+                    .after(TerminalSystem::DrawWorld),
+            )
+            .add_system_to_stage(
+                CoreStage::Last,
+                flush_terminal.after(TerminalSystem::DrawOverlay),
+            );
+    }
+}
+
+/// Keys bevy should see as pressed for a key reported by the terminal
+fn translate_key(code: event::KeyCode) -> &'static [KeyCode] {
+    match code {
+        event::KeyCode::Char(char) => match char.to_ascii_lowercase() {
+            'w' => &[KeyCode::W],
+            'a' => &[KeyCode::A],
+            's' => &[KeyCode::S],
+            'd' => &[KeyCode::D],
+            // Terminals only repeat the last key held, so diagonals get keys of their own
+            'q' => &[KeyCode::W, KeyCode::A],
+            'e' => &[KeyCode::W, KeyCode::D],
+            'z' => &[KeyCode::S, KeyCode::A],
+            'c' => &[KeyCode::S, KeyCode::D],
+            '1' => &[KeyCode::Key1],
+            '2' => &[KeyCode::Key2],
+            '3' => &[KeyCode::Key3],
+            _ => &[],
+        },
+        event::KeyCode::Up => &[KeyCode::Up],
+        event::KeyCode::Down => &[KeyCode::Down],
+        event::KeyCode::Left => &[KeyCode::Left],
+        event::KeyCode::Right => &[KeyCode::Right],
+        event::KeyCode::Enter => &[KeyCode::Return],
+        event::KeyCode::Esc => &[KeyCode::Escape],
+        _ => &[],
+    }
+}
+
+/// Turns the terminal's key presses into the keyboard events bevy's input handling expects
+fn read_terminal_keys(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    mut held: ResMut<HeldKeys>,
+    mut keyboard_events: EventWriter<KeyboardInput>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    let now = time.seconds_since_startup();
+    let mut send = |key_code, state| {
+        keyboard_events.send(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(key_code),
+            state,
+        })
+    };
+
+    // Only movement during play is held, so keys in menus can be tapped repeatedly
+    let playing = *state.current() == AppState::Playing;
+
+    while event::poll(Duration::ZERO).unwrap_or(false) {
+        let key = match event::read() {
+            Ok(Event::Key(key)) => key,
+            _ => continue,
+        };
+
+        // Raw mode keeps ctrl-c from interrupting the game
+        if key.code == event::KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            app_exit_events.send(AppExit);
+            continue;
+        }
+
+        let keys = translate_key(key.code);
+        let movement = !keys.is_empty() && keys.iter().all(|key| MOVEMENT_KEYS.contains(key));
+        if movement {
+            // A new direction takes over from the last one right away
+            held.0.retain(|key_code, _| {
+                let keep = !MOVEMENT_KEYS.contains(key_code) || keys.contains(key_code);
+                if !keep {
+                    send(*key_code, ElementState::Released);
+                }
+                keep
+            });
+        }
+
+        let until = if playing && movement {
+            now + MOVEMENT_HOLD
+        } else {
+            now
+        };
+        for key_code in keys {
+            if held.0.insert(*key_code, until).is_none() {
+                send(*key_code, ElementState::Pressed);
+            }
+        }
+    }
+
+    // Keys that were only tapped are pressed and released within the same frame
+    held.0.retain(|key_code, until| {
+        let keep = *until > now;
+        if !keep {
+            send(*key_code, ElementState::Released);
+        }
+        keep
+    });
+}
+
+fn sample_terminal_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    screen: Res<TerminalScreen>,
+    mut tick_input: ResMut<TickInput>,
+) {
+    tick_input.movement = keyboard_movement(&keyboard_input);
+    tick_input.view_half_extents = Some(screen.view_half_extents());
+}
+
+/// Resumes with escape, or quits to the main menu with enter
+fn paused_input(mut keyboard_input: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        state.pop().unwrap();
+        // Otherwise `pause_input` sees the same press and pauses again
+        keyboard_input.reset(KeyCode::Escape);
+    } else if keyboard_input.just_pressed(KeyCode::Return) {
+        state.replace(AppState::MainMenu).unwrap();
+        keyboard_input.reset(KeyCode::Return);
+    }
+}
+
+fn terminal_color(color: Color) -> style::Color {
+    let [r, g, b, _] = color.as_rgba_f32();
+    style::Color::Rgb {
+        r: (r * 255.0) as u8,
+        g: (g * 255.0) as u8,
+        b: (b * 255.0) as u8,
+    }
+}
+
+/// Draws the world around the player, later entries covering earlier ones: obstacles, gems,
+/// projectiles, monsters and the player on top
+#[allow(clippy::type_complexity)]
+fn draw_world(
+    mut screen: ResMut<TerminalScreen>,
+    archetypes: Res<Assets<MonsterArchetype>>,
+    player_query: Query<(&Transform, &Sprite), With<Player>>,
+    obstacle_query: Query<(&Transform, &Sprite), With<Obstacle>>,
+    gem_query: Query<(&Transform, &Sprite), With<ExperienceGem>>,
+    projectile_query: Query<(&Transform, &Sprite), With<Projectile>>,
+    monster_query: Query<(&Transform, &Sprite, &MonsterKind), With<Monster>>,
+) {
+    let screen = &mut *screen;
+    let size = terminal::size().unwrap_or((80, 24));
+    if screen.size != size {
+        screen.size = size;
+        screen.drawn.clear();
+    }
+    let cells = size.0 as usize * size.1 as usize;
+    screen.cells.clear();
+    screen.cells.resize(cells, BLANK);
+
+    // The world is only drawn while there is a run to show
+    let (player, player_sprite) = match player_query.get_single() {
+        Ok((transform, sprite)) => (transform.translation.truncate(), sprite),
+        Err(_) => return,
+    };
+
+    let grid = screen.grid();
+    for (transform, sprite) in obstacle_query.iter() {
+        let cell = Cell {
+            glyph: '#',
+            color: terminal_color(sprite.color),
+        };
+        for index in grid.obstacle_cells(player, transform) {
+            screen.cells[index] = cell;
+        }
+    }
+
+    let put = |screen: &mut TerminalScreen, transform: &Transform, glyph: char, color: Color| {
+        let color = terminal_color(color);
+        screen.put(
+            transform.translation.truncate() - player,
+            Cell { glyph, color },
+        );
+    };
+
+    for (transform, sprite) in gem_query.iter() {
+        put(screen, transform, '+', sprite.color);
+    }
+    for (transform, sprite) in projectile_query.iter() {
+        put(screen, transform, '*', sprite.color);
+    }
+    for (transform, sprite, kind) in monster_query.iter() {
+        // Monsters show as the first letter of their name
+        let glyph = archetypes
+            .get(&kind.0)
+            .and_then(|archetype| archetype.name.chars().next())
+            .unwrap_or('M');
+        put(screen, transform, glyph, sprite.color);
+    }
+
+    let color = terminal_color(player_sprite.color);
+    screen.put(Vec2::ZERO, Cell { glyph: '@', color });
+}
+
+/// Writes the menu of the current state and the status line over the world
+fn draw_overlay(
+    mut screen: ResMut<TerminalScreen>,
+    state: Res<State<AppState>>,
+    choices: Res<UpgradeChoices>,
+    run_time: Res<RunTime>,
+    run_rng: Res<RunRng>,
+    kill_count: Res<KillCount>,
+    player_query: Query<(&Health, &MaxHealth, &Level), With<Player>>,
+) {
+    let seconds = run_time.0.as_secs();
+    let time = format!("{}:{:02}", seconds / 60, seconds % 60);
+    let player = player_query.get_single().ok();
+
+    let lines = match state.current() {
+        AppState::MainMenu => vec![
+            "VAMPS".to_string(),
+            String::new(),
+            "Enter to start, Esc to quit".to_string(),
+            "Move with WASD or the arrow keys, Q E Z C diagonally".to_string(),
+        ],
+        AppState::Playing => Vec::new(),
+        AppState::Paused => vec![
+            "Paused".to_string(),
+            String::new(),
+            "Esc to resume, Enter to quit to the main menu".to_string(),
+        ],
+        AppState::LevelUp => {
+            let mut lines = vec!["Level up!".to_string(), String::new()];
+            for (index, upgrade) in choices.options().iter().enumerate() {
+                let marker = if index == choices.selected() {
+                    '>'
+                } else {
+                    ' '
+                };
+                lines.push(format!(
+                    "{} {}. {}",
+                    marker,
+                    index + 1,
+                    upgrade.description()
+                ));
+            }
+            lines.push(String::new());
+            lines.push("Pick with 1-3, or the arrow keys and Enter".to_string());
+            lines
+        }
+        AppState::GameOver => vec![
+            "Game over".to_string(),
+            String::new(),
+            format!(
+                "Survived {}, reached level {}, {} kills",
+                time,
+                player.map_or(0, |(_, _, level)| level.0),
+                kill_count.0
+            ),
+            format!("Seed {}", run_rng.seed()),
+            String::new(),
+            "Enter to play again, Esc for the main menu".to_string(),
+        ],
+    };
+
+    let top = (screen.world_rows() as usize).saturating_sub(lines.len()) / 2;
+    for (index, line) in lines.iter().enumerate() {
+        screen.text((top + index) as u16, line);
+    }
+
+    if let Some((health, max_health, level)) = player {
+        let status = format!(
+            "HP {}/{}   Level {}   {}   {} kills",
+            health.0.max(0.0).ceil(),
+            max_health.0.ceil(),
+            level.0,
+            time,
+            kill_count.0
+        );
+        let row = screen.world_rows();
+        screen.text(row, &status);
+    }
+}
+
+/// Writes out the cells that changed since the last frame
+fn flush_terminal(mut screen: ResMut<TerminalScreen>) {
+    let mut stdout = stdout();
+    let columns = screen.size.0 as usize;
+
+    // After a resize everything is drawn again
+    let redraw = screen.drawn.len() != screen.cells.len();
+    if redraw {
+        queue!(stdout, Clear(ClearType::All)).expect("terminal should be writable");
+    }
+
+    for (index, cell) in screen.cells.iter().enumerate() {
+        if !redraw && screen.drawn[index] == *cell {
+            continue;
+        }
+        let (column, row) = ((index % columns) as u16, (index / columns) as u16);
+        queue!(
+            stdout,
+            MoveTo(column, row),
+            SetForegroundColor(cell.color),
+            Print(cell.glyph)
+        )
+        .expect("terminal should be writable");
+    }
+    stdout.flush().expect("terminal should be writable");
+
+    let screen = &mut *screen;
+    screen.drawn.clone_from(&screen.cells);
+}
@@ -0,0 +1,206 @@
+use bevy::{
+    app::Events,
+    asset::{AssetServer, Assets, Handle},
+    core::DefaultTaskPoolOptions,
+    ecs::{
+        query::{FilterFetch, WorldQuery},
+        system::CommandQueue,
+    },
+    input::Input,
+    math::Vec2,
+    prelude::{
+        App, Commands, Component, DespawnRecursiveExt, Entity, KeyCode, Res, ResMut, State,
+        Transform, With,
+    },
+};
+use bevy_rapier2d::physics::RapierConfiguration;
+
+use crate::{
+    archetype::{wait_for_archetypes, MonsterArchetype, MonsterArchetypes},
+    input::{keyboard_movement, TickInput},
+    player::spawn_player_body,
+    spawner::{spawn_monster, MonsterSpawner},
+    AddGameplaySystem, AppState, FixedTime, HeadlessPlugins, Player, RunSettings, TickPhase,
+    VampsPlugin,
+};
+
+/// The gameplay plugins without a window, advanced a tick at a time by tests. Starts out in a run
+/// with an empty world: tests spawn the player and monsters where they need them, and no monsters
+/// spawn on their own. The world's obstacles are generated as usual, clear of the origin.
+///
+/// Every system looking for the player expects exactly one, so spawn it before the first tick.
+pub struct TestApp {
+    app: App,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let mut app = App::new();
+        app.insert_resource(DefaultTaskPoolOptions::with_num_threads(1))
+            .add_plugins(HeadlessPlugins)
+            .add_plugin(VampsPlugin)
+            .insert_resource(RunSettings { seed: Some(0) })
+            .add_gameplay_system(TickPhase::Input, sample_keyboard);
+        wait_for_archetypes(&mut app);
+
+        app.world
+            .get_resource_mut::<State<AppState>>()
+            .unwrap()
+            .set(AppState::Playing)
+            .unwrap();
+        app.update();
+
+        // The run's player is replaced by whichever player the test spawns
+        let world = &mut app.world;
+        world
+            .get_resource_mut::<MonsterSpawner>()
+            .unwrap()
+            .max_monsters = 0;
+        let players = world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, world);
+        for player in players {
+            commands.entity(player).despawn_recursive();
+        }
+        queue.apply(world);
+
+        Self { app }
+    }
+
+    /// Spawns the player at `position`, without any weapons
+    pub fn spawn_player(&mut self, position: Vec2) -> Entity {
+        let world = &self.app.world;
+        let rapier_config = world.get_resource::<RapierConfiguration>().unwrap();
+
+        let mut queue = CommandQueue::default();
+        let player = spawn_player_body(
+            &mut Commands::new(&mut queue, world),
+            rapier_config,
+            position,
+        );
+        queue.apply(&mut self.app.world);
+        player
+    }
+
+    /// Spawns a monster of the archetype called `name` at `position`
+    pub fn spawn_monster(&mut self, name: &str, position: Vec2) -> Entity {
+        let world = &self.app.world;
+        let handle = self.archetype_handle(name);
+        let archetype = world
+            .get_resource::<Assets<MonsterArchetype>>()
+            .unwrap()
+            .get(&handle)
+            .unwrap();
+
+        let mut queue = CommandQueue::default();
+        let monster = spawn_monster(
+            &mut Commands::new(&mut queue, world),
+            world.get_resource::<AssetServer>().unwrap(),
+            world.get_resource::<RapierConfiguration>().unwrap(),
+            &handle,
+            archetype,
+            position,
+        );
+        queue.apply(&mut self.app.world);
+        monster
+    }
+
+    /// The archetype called `name`
+    pub fn archetype(&self, name: &str) -> &MonsterArchetype {
+        let handle = self.archetype_handle(name);
+        let archetypes = self.resource::<Assets<MonsterArchetype>>();
+        archetypes.get(&handle).unwrap()
+    }
+
+    fn archetype_handle(&self, name: &str) -> Handle<MonsterArchetype> {
+        let archetypes = self.resource::<Assets<MonsterArchetype>>();
+        self.resource::<MonsterArchetypes>()
+            .0
+            .iter()
+            .find(|handle| archetypes.get(*handle).unwrap().name == name)
+            .unwrap_or_else(|| panic!("there should be a monster archetype called {}", name))
+            .clone()
+    }
+
+    /// Holds `key` down until it is released
+    pub fn press(&mut self, key: KeyCode) {
+        let mut keyboard_input = self.app.world.get_resource_mut::<Input<KeyCode>>().unwrap();
+        keyboard_input.press(key);
+    }
+
+    /// Sends `event` for the next tick to read
+    pub fn send<T: Send + Sync + 'static>(&mut self, event: T) {
+        self.app
+            .world
+            .get_resource_mut::<Events<T>>()
+            .unwrap()
+            .send(event);
+    }
+
+    /// Runs `ticks` gameplay ticks, a frame each. Ticks stop once the run leaves `Playing`, e.g.
+    /// for a level up.
+    pub fn tick(&mut self, ticks: u32) {
+        self.tick_frames(ticks, 1);
+    }
+
+    /// Runs `frames` frames of `ticks_per_frame` gameplay ticks each, like a slow machine catching
+    /// up with the simulation
+    pub fn tick_frames(&mut self, frames: u32, ticks_per_frame: u32) {
+        for _ in 0..frames {
+            let mut fixed_time = self.app.world.get_resource_mut::<FixedTime>().unwrap();
+            fixed_time.accumulator = fixed_time.step * ticks_per_frame;
+            self.app.update();
+        }
+    }
+
+    /// Runs a frame without a tick, e.g. for a state change asked for during the last tick to
+    /// take effect
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.app.world.get::<T>(entity)
+    }
+
+    pub fn resource<T: Send + Sync + 'static>(&self) -> &T {
+        self.app.world.get_resource::<T>().unwrap()
+    }
+
+    pub fn position(&self, entity: Entity) -> Vec2 {
+        let transform = self
+            .get::<Transform>(entity)
+            .expect("entity should exist and have a position");
+        transform.translation.truncate()
+    }
+
+    /// Whether `entity` is still around, e.g. to check that it was despawned
+    pub fn exists(&self, entity: Entity) -> bool {
+        self.app.world.get_entity(entity).is_some()
+    }
+
+    /// Transforms of every entity matching the filter `F`
+    pub fn transforms<F: WorldQuery>(&mut self) -> Vec<Transform>
+    where
+        F::Fetch: FilterFetch,
+    {
+        let world = &mut self.app.world;
+        world
+            .query_filtered::<&Transform, F>()
+            .iter(world)
+            .copied()
+            .collect()
+    }
+
+    pub fn state(&self) -> &AppState {
+        self.resource::<State<AppState>>().current()
+    }
+}
+
+fn sample_keyboard(keyboard_input: Res<Input<KeyCode>>, mut tick_input: ResMut<TickInput>) {
+    tick_input.movement = keyboard_movement(&keyboard_input);
+}
@@ -0,0 +1,124 @@
+use bevy::{
+    ecs::system::EntityCommands,
+    input::Input,
+    prelude::{
+        AlignItems, BuildChildren, ChildBuilder, Color, Commands, Component, FlexDirection,
+        GamepadButton, GamepadButtonType, Gamepads, JustifyContent, KeyCode, NodeBundle, Rect,
+        Size, Style, Text, TextBundle, TextStyle, UiColor, Val,
+    },
+};
+
+use crate::UiFont;
+
+/// Lays children out from the top down. Bevy's UI grows upwards from the bottom left corner, so
+/// plain column layouts would put the first child at the bottom.
+pub const COLUMN: FlexDirection = FlexDirection::ColumnReverse;
+
+const BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.2);
+const SELECTED_BUTTON_COLOR: Color = Color::rgb(0.3, 0.3, 0.45);
+
+pub fn text_style(font: &UiFont, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: font.0.clone(),
+        font_size,
+        color: Color::WHITE,
+    }
+}
+
+/// Spawns a screen covering the window in `backdrop`, with a column of `background` in the middle
+/// of it for `spawn_contents` to fill in. Returns the screen, to mark it for despawning.
+pub fn spawn_panel<'w, 's, 'a>(
+    commands: &'a mut Commands<'w, 's>,
+    backdrop: Color,
+    background: Color,
+    align_items: AlignItems,
+    spawn_contents: impl FnOnce(&mut ChildBuilder),
+) -> EntityCommands<'w, 's, 'a> {
+    let mut screen = commands.spawn_bundle(NodeBundle {
+        style: Style {
+            size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        color: backdrop.into(),
+        ..Default::default()
+    });
+    screen.with_children(|screen| {
+        screen
+            .spawn_bundle(NodeBundle {
+                style: Style {
+                    flex_direction: COLUMN,
+                    align_items,
+                    padding: Rect::all(Val::Px(20.0)),
+                    ..Default::default()
+                },
+                color: background.into(),
+                ..Default::default()
+            })
+            .with_children(spawn_contents);
+    });
+    screen
+}
+
+pub fn spawn_title(panel: &mut ChildBuilder, title: &str, text_style: TextStyle) {
+    panel.spawn_bundle(TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(10.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(title, text_style, Default::default()),
+        ..Default::default()
+    });
+}
+
+/// Spawns one of the options of a menu, marked with `marker` to recolour it with `button_color`
+/// when the selection moves
+pub fn spawn_button(
+    panel: &mut ChildBuilder,
+    label: &str,
+    text_style: TextStyle,
+    selected: bool,
+    marker: impl Component,
+) {
+    panel
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: Rect::all(Val::Px(5.0)),
+                padding: Rect::all(Val::Px(10.0)),
+                ..Default::default()
+            },
+            color: button_color(selected),
+            ..Default::default()
+        })
+        .insert(marker)
+        .with_children(|button| {
+            button.spawn_bundle(TextBundle {
+                text: Text::with_section(label, text_style, Default::default()),
+                ..Default::default()
+            });
+        });
+}
+
+pub fn button_color(selected: bool) -> UiColor {
+    if selected {
+        SELECTED_BUTTON_COLOR.into()
+    } else {
+        BUTTON_COLOR.into()
+    }
+}
+
+/// Checks whether a key was just pressed, or the matching button on any of the gamepads, the way
+/// menus and other controls outside of the gameplay take their input
+pub fn menu_pressed<'a>(
+    keys: &'a Input<KeyCode>,
+    gamepads: &'a Gamepads,
+    buttons: &'a Input<GamepadButton>,
+) -> impl Fn(KeyCode, GamepadButtonType) -> bool + 'a {
+    move |key, button| {
+        keys.just_pressed(key)
+            || gamepads
+                .iter()
+                .any(|gamepad| buttons.just_pressed(GamepadButton(*gamepad, button)))
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::VecDeque;
+
+use bevy::{
+    input::Input,
+    log::error,
+    prelude::{
+        AlignItems, BuildChildren, Color, Commands, Component, DespawnRecursiveExt, Entity,
+        EventReader, GamepadButton, GamepadButtonType, Gamepads, In, KeyCode, Query, Res, ResMut,
+        State, UiColor, With,
+    },
+};
+use rand::seq::SliceRandom;
+
+use crate::{
+    headless::Bot,
+    progression::{LevelUp, PickupRadius},
+    replay::{Playback, Recorder},
+    rng::RunRng,
+    ui::{button_color, menu_pressed, spawn_button, spawn_panel, spawn_title, text_style},
+    weapon::{Weapon, WeaponKind, MAX_WEAPONS, MAX_WEAPON_LEVEL},
+    AppState, Health, MaxHealth, MoveSpeed, Player, UiFont,
+};
+
+/// How many upgrades are offered per level
+const CHOICES: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Upgrade {
+    NewWeapon(WeaponKind),
+    WeaponLevel(WeaponKind),
+    MaxHealth,
+    MoveSpeed,
+    PickupRadius,
+}
+
+impl Upgrade {
+    pub fn description(&self) -> String {
+        match self {
+            Upgrade::NewWeapon(kind) => format!("New weapon: {}", kind.name()),
+            Upgrade::WeaponLevel(kind) => format!("{} level up", kind.name()),
+            Upgrade::MaxHealth => "Max health +20".to_string(),
+            Upgrade::MoveSpeed => "Move speed +10%".to_string(),
+            Upgrade::PickupRadius => "Pickup radius +30%".to_string(),
+        }
+    }
+}
+
+/// How likely each upgrade is to be offered on level up. Weapon upgrades are only offered while
+/// they still apply, i.e. for weapons not yet carried or not yet at their maximum level.
+pub struct UpgradePool {
+    pub new_weapon: f32,
+    pub weapon_level: f32,
+    pub passives: Vec<(Upgrade, f32)>,
+}
+
+impl Default for UpgradePool {
+    fn default() -> Self {
+        Self {
+            new_weapon: 1.5,
+            weapon_level: 3.0,
+            passives: vec![
+                (Upgrade::MaxHealth, 1.0),
+                (Upgrade::MoveSpeed, 1.0),
+                (Upgrade::PickupRadius, 1.0),
+            ],
+        }
+    }
+}
+
+impl UpgradePool {
+    /// Upgrades that apply to a player carrying `weapons`, given as kind and level
+    fn candidates(&self, weapons: &[(WeaponKind, u32)]) -> Vec<(Upgrade, f32)> {
+        let mut candidates = self.passives.clone();
+
+        for (kind, level) in weapons {
+            if *level < MAX_WEAPON_LEVEL {
+                candidates.push((Upgrade::WeaponLevel(*kind), self.weapon_level));
+            }
+        }
+
+        if weapons.len() < MAX_WEAPONS {
+            for kind in WeaponKind::ALL {
+                if !weapons.iter().any(|(carried, _)| *carried == kind) {
+                    candidates.push((Upgrade::NewWeapon(kind), self.new_weapon));
+                }
+            }
+        }
+
+        candidates
+    }
+}
+
+/// Levels reached that still need an upgrade chosen, oldest first
+#[derive(Default)]
+pub struct PendingLevelUps(pub VecDeque<u32>);
+
+#[derive(Default)]
+pub struct UpgradeChoices {
+    options: Vec<Upgrade>,
+    selected: usize,
+}
+
+impl UpgradeChoices {
+    pub fn options(&self) -> &[Upgrade] {
+        &self.options
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+}
+
+#[derive(Component)]
+pub struct LevelUpScreen;
+
+#[derive(Component)]
+pub struct UpgradeOption(usize);
+
+/// Drops level ups left over from the last run
+pub fn clear_level_ups(mut pending: ResMut<PendingLevelUps>) {
+    pending.0.clear();
+}
+
+pub fn queue_level_ups(
+    mut level_up_events: EventReader<LevelUp>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut state: ResMut<State<AppState>>,
+) {
+    pending
+        .0
+        .extend(level_up_events.iter().map(|level_up| level_up.level));
+
+    // A state change asked for earlier in the frame, e.g. by the player dying, goes first. The
+    // level up is pushed again the next time `Playing` is updated.
+    if !pending.0.is_empty() {
+        let _ = state.push(AppState::LevelUp);
+    }
+}
+
+pub fn spawn_level_up_screen(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    pool: Res<UpgradePool>,
+    pending: Res<PendingLevelUps>,
+    mut run_rng: ResMut<RunRng>,
+    mut choices: ResMut<UpgradeChoices>,
+    weapon_query: Query<&Weapon>,
+) {
+    let weapons = weapon_query
+        .iter()
+        .map(|weapon| (weapon.kind, weapon.level))
+        .collect::<Vec<_>>();
+    roll_choices(&pool, &weapons, &mut run_rng, &mut choices);
+
+    let level = pending.0.front().copied().unwrap_or_default();
+    spawn_choices(&mut commands, &font, &choices, level);
+}
+
+pub fn despawn_level_up_screen(
+    mut commands: Commands,
+    screen_query: Query<Entity, With<LevelUpScreen>>,
+) {
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn_recursive();
+    }
+}
+
+/// Picks one of the options on the level up screen, from the player's input, the replay being
+/// played back or the bot, for `select_upgrade` to apply
+pub fn choose_upgrade(
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    playback: Option<ResMut<Playback>>,
+    bot: Option<Res<Bot>>,
+    mut choices: ResMut<UpgradeChoices>,
+    mut option_query: Query<(&UpgradeOption, &mut UiColor)>,
+) -> Option<usize> {
+    let pressed = menu_pressed(&keyboard_input, &gamepads, &gamepad_input);
+
+    // With nothing to pick from, `select_upgrade` lets the level pass right away
+    let count = choices.options.len();
+    if count == 0 {
+        return None;
+    }
+
+    let mut chosen = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3]
+        .iter()
+        .position(|key| keyboard_input.just_pressed(*key))
+        .filter(|index| *index < count);
+
+    if pressed(KeyCode::Up, GamepadButtonType::DPadUp) {
+        choices.selected = (choices.selected + count - 1) % count;
+    }
+    if pressed(KeyCode::Down, GamepadButtonType::DPadDown) {
+        choices.selected = (choices.selected + 1) % count;
+    }
+    if pressed(KeyCode::Return, GamepadButtonType::South) {
+        chosen = Some(choices.selected);
+    }
+
+    // Replays pick whatever was picked in the recording, falling back to the first option once
+    // it runs out
+    if let Some(mut playback) = playback {
+        chosen = Some(
+            playback
+                .next_upgrade()
+                .filter(|index| *index < count)
+                .unwrap_or(0),
+        );
+    }
+    // Headless runs leave the pick to the bot
+    if let Some(bot) = bot {
+        chosen = Some(bot.pick_upgrade(&choices.options));
+    }
+
+    if chosen.is_none() {
+        for (option, mut color) in option_query.iter_mut() {
+            *color = button_color(option.0 == choices.selected);
+        }
+    }
+
+    chosen
+}
+
+/// Applies the upgrade picked by `choose_upgrade`, chained in front of it
+#[allow(clippy::too_many_arguments)]
+pub fn select_upgrade(
+    In(chosen): In<Option<usize>>,
+    mut commands: Commands,
+    font: Res<UiFont>,
+    pool: Res<UpgradePool>,
+    mut run_rng: ResMut<RunRng>,
+    recorder: Option<ResMut<Recorder>>,
+    mut choices: ResMut<UpgradeChoices>,
+    mut pending: ResMut<PendingLevelUps>,
+    mut state: ResMut<State<AppState>>,
+    mut player_query: Query<
+        (
+            Entity,
+            &mut Health,
+            &mut MaxHealth,
+            &mut MoveSpeed,
+            &mut PickupRadius,
+        ),
+        With<Player>,
+    >,
+    mut weapon_query: Query<&mut Weapon>,
+    screen_query: Query<Entity, With<LevelUpScreen>>,
+) {
+    // Without any upgrade to offer, e.g. with `UpgradePool` configured down to nothing, the level
+    // passes without one
+    if chosen.is_none() && !choices.options.is_empty() {
+        return;
+    }
+
+    if let (Some(chosen), Some(mut recorder)) = (chosen, recorder) {
+        recorder.record_upgrade(chosen);
+    }
+
+    let (player, mut health, mut max_health, mut move_speed, mut pickup_radius) =
+        player_query.single_mut();
+    let upgrade = chosen.map(|chosen| choices.options[chosen]);
+    match upgrade {
+        Some(Upgrade::NewWeapon(kind)) => {
+            commands.entity(player).with_children(|player| {
+                player.spawn().insert(Weapon::new(kind));
+            });
+        }
+        Some(Upgrade::WeaponLevel(kind)) => {
+            for mut weapon in weapon_query.iter_mut() {
+                if weapon.kind == kind {
+                    weapon.level_up();
+                }
+            }
+        }
+        Some(Upgrade::MaxHealth) => {
+            max_health.0 += 20.0;
+            health.0 += 20.0;
+        }
+        Some(Upgrade::MoveSpeed) => move_speed.0 *= 1.1,
+        Some(Upgrade::PickupRadius) => pickup_radius.0 *= 1.3,
+        None => {}
+    }
+
+    pending.0.pop_front();
+    if let Some(&level) = pending.0.front() {
+        // Offer a fresh set of upgrades for the next level without leaving the screen
+        for screen in screen_query.iter() {
+            commands.entity(screen).despawn_recursive();
+        }
+        let mut weapons = weapon_query
+            .iter()
+            .map(|weapon| (weapon.kind, weapon.level))
+            .collect::<Vec<_>>();
+
+        // A weapon picked just now is only spawned once commands are applied
+        if let Some(Upgrade::NewWeapon(kind)) = upgrade {
+            weapons.push((kind, 1));
+        }
+
+        roll_choices(&pool, &weapons, &mut run_rng, &mut choices);
+        spawn_choices(&mut commands, &font, &choices, level);
+    } else {
+        state.pop().unwrap();
+    }
+}
+
+fn roll_choices(
+    pool: &UpgradePool,
+    weapons: &[(WeaponKind, u32)],
+    run_rng: &mut RunRng,
+    choices: &mut UpgradeChoices,
+) {
+    let candidates = pool.candidates(weapons);
+    let options =
+        candidates.choose_multiple_weighted(&mut run_rng.upgrades, CHOICES, |(_, weight)| *weight);
+
+    // A misconfigured `UpgradePool` offers nothing rather than ending the run
+    choices.options = match options {
+        Ok(options) => options.map(|(upgrade, _)| *upgrade).collect(),
+        Err(err) => {
+            error!("couldn't pick upgrades from the upgrade pool: {}", err);
+            Vec::new()
+        }
+    };
+    choices.selected = 0;
+}
+
+fn spawn_choices(commands: &mut Commands, font: &UiFont, choices: &UpgradeChoices, level: u32) {
+    spawn_panel(
+        commands,
+        Color::rgba(0.0, 0.0, 0.0, 0.6),
+        Color::rgb(0.08, 0.08, 0.1),
+        AlignItems::Stretch,
+        |panel| {
+            spawn_title(panel, &format!("Level {}!", level), text_style(font, 40.0));
+
+            for (index, upgrade) in choices.options.iter().enumerate() {
+                spawn_button(
+                    panel,
+                    &format!("{}. {}", index + 1, upgrade.description()),
+                    text_style(font, 28.0),
+                    index == choices.selected,
+                    UpgradeOption(index),
+                );
+            }
+        },
+    )
+    .insert(LevelUpScreen);
+}
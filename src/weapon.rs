@@ -0,0 +1,343 @@
+use std::cmp::Reverse;
+
+use bevy::{
+    core::Timer,
+    math::{Vec2, Vec3},
+    prelude::{Color, Commands, Component, Parent, Query, Res, ResMut, Transform, With},
+    sprite::{Sprite, SpriteBundle},
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    physics::{ColliderBundle, ColliderPositionSync, RapierConfiguration, RigidBodyBundle},
+    prelude::{
+        ActiveCollisionTypes, ActiveEvents, ColliderFlags, ColliderShape, ColliderType,
+        RigidBodyType,
+    },
+};
+use ordered_float::OrderedFloat;
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{
+    rng::RunRng, spatial::SpatialIndex, Facing, FixedTime, Health, Monster, Player, Projectile,
+    RunEntity,
+};
+
+/// Highest level a weapon can be upgraded to
+pub const MAX_WEAPON_LEVEL: u32 = 8;
+
+/// Most weapons the player can carry at once
+pub const MAX_WEAPONS: usize = 4;
+
+/// Side length of a projectile with an area of 1.0
+const PROJECTILE_SIZE: f32 = 10.0;
+
+/// Distance from the player's center at which projectiles appear
+const MUZZLE_OFFSET: f32 = 28.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WeaponKind {
+    Wand,
+    Knife,
+    Axe,
+    Firebomb,
+}
+
+impl WeaponKind {
+    pub const ALL: [WeaponKind; 4] = [
+        WeaponKind::Wand,
+        WeaponKind::Knife,
+        WeaponKind::Axe,
+        WeaponKind::Firebomb,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeaponKind::Wand => "Wand",
+            WeaponKind::Knife => "Knife",
+            WeaponKind::Axe => "Axe",
+            WeaponKind::Firebomb => "Firebomb",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            WeaponKind::Wand => Color::rgb(0.2, 0.5, 0.2),
+            WeaponKind::Knife => Color::rgb(0.8, 0.8, 0.85),
+            WeaponKind::Axe => Color::rgb(0.6, 0.4, 0.2),
+            WeaponKind::Firebomb => Color::rgb(0.9, 0.4, 0.1),
+        }
+    }
+}
+
+/// How a weapon picks where to fire each projectile of a volley
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetingStrategy {
+    Nearest,
+    Random,
+    LowestHealth,
+    HighestHealth,
+    Furthest,
+    /// Fires along the direction the player last moved in, fanning out extra projectiles
+    Facing,
+}
+
+/// Angle between projectiles fanned out by `TargetingStrategy::Facing`
+const FACING_SPREAD: f32 = 0.15;
+
+/// A weapon carried by the player, living on a child entity of the player
+#[derive(Component)]
+pub struct Weapon {
+    pub kind: WeaponKind,
+    pub level: u32,
+    pub cooldown: Timer,
+    pub damage: f32,
+    /// Units per second
+    pub projectile_speed: f32,
+    /// Projectiles fired per volley
+    pub amount: usize,
+    /// Monsters each projectile can hit
+    pub pierce: usize,
+    /// Multiplier on projectile size
+    pub area: f32,
+    /// Distance projectiles travel before they are despawned
+    pub range: f32,
+    /// Monsters this close to a projectile's hit take its damage too, nothing but the hit monster
+    /// when zero
+    pub blast_radius: f32,
+    pub targeting: TargetingStrategy,
+}
+
+impl Weapon {
+    pub fn new(kind: WeaponKind) -> Self {
+        let (cooldown, damage, projectile_speed, amount, pierce, area, range) = match kind {
+            WeaponKind::Wand => (0.5, 10.0, 240.0, 1, 1, 1.0, 1000.0),
+            WeaponKind::Knife => (0.3, 6.0, 420.0, 1, 1, 0.8, 800.0),
+            WeaponKind::Axe => (1.4, 25.0, 180.0, 1, 4, 2.0, 600.0),
+            WeaponKind::Firebomb => (2.0, 15.0, 200.0, 1, 1, 1.5, 500.0),
+        };
+        let blast_radius = match kind {
+            WeaponKind::Firebomb => 60.0,
+            _ => 0.0,
+        };
+        let targeting = match kind {
+            WeaponKind::Wand => TargetingStrategy::Nearest,
+            WeaponKind::Knife => TargetingStrategy::Facing,
+            WeaponKind::Axe => TargetingStrategy::HighestHealth,
+            WeaponKind::Firebomb => TargetingStrategy::Random,
+        };
+
+        Self {
+            kind,
+            level: 1,
+            cooldown: Timer::from_seconds(cooldown, true),
+            damage,
+            projectile_speed,
+            amount,
+            pierce,
+            area,
+            range,
+            blast_radius,
+            targeting,
+        }
+    }
+
+    /// Raises the weapon one level. Every level adds damage, and alternating levels add either
+    /// an extra projectile or pierce along with a shorter cooldown.
+    pub fn level_up(&mut self) {
+        self.level += 1;
+        self.damage *= 1.15;
+
+        if self.level % 2 == 0 {
+            self.amount += 1;
+        } else {
+            self.pierce += 1;
+            let duration = self.cooldown.duration().mul_f32(0.9);
+            self.cooldown.set_duration(duration);
+        }
+
+        if self.level % 4 == 0 {
+            self.area *= 1.25;
+            self.blast_radius *= 1.25;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn fire_weapons(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    rapier_config: Res<RapierConfiguration>,
+    monster_index: Res<SpatialIndex<Monster>>,
+    mut run_rng: ResMut<RunRng>,
+    player_query: Query<(&Transform, &Facing), With<Player>>,
+    monster_query: Query<(&Transform, &Health), With<Monster>>,
+    mut weapon_query: Query<(&Parent, &mut Weapon)>,
+) {
+    for (parent, mut weapon) in weapon_query.iter_mut() {
+        // Attack when the weapon's cooldown elapses
+        if !weapon.cooldown.tick(fixed_time.step).just_finished() {
+            continue;
+        }
+
+        let (player_transform, facing) = match player_query.get(parent.0) {
+            Ok(player) => player,
+            Err(_) => continue,
+        };
+        let origin = player_transform.translation.truncate();
+
+        let directions = match weapon.targeting {
+            TargetingStrategy::Facing => {
+                let facing_angle = facing.0.y.atan2(facing.0.x);
+                let spread = (weapon.amount - 1) as f32 * FACING_SPREAD;
+                (0..weapon.amount)
+                    .map(|i| {
+                        let angle = facing_angle + i as f32 * FACING_SPREAD - spread / 2.0;
+                        Vec2::new(angle.cos(), angle.sin())
+                    })
+                    .collect()
+            }
+            strategy => {
+                let targets = select_targets(
+                    strategy,
+                    weapon.amount,
+                    origin,
+                    &monster_index,
+                    &monster_query,
+                    &mut run_rng.targeting,
+                );
+
+                // With fewer monsters than projectiles the volley doubles up on targets
+                targets
+                    .iter()
+                    .cycle()
+                    .take(if targets.is_empty() { 0 } else { weapon.amount })
+                    .map(|target| (*target - origin).normalize_or_zero())
+                    .filter(|direction| *direction != Vec2::ZERO)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        for direction in directions {
+            spawn_projectile(
+                &mut commands,
+                &rapier_config,
+                &weapon,
+                player_transform.translation,
+                direction.extend(0.0),
+            );
+        }
+    }
+}
+
+/// Positions of up to `amount` distinct monsters picked by `strategy`
+fn select_targets(
+    strategy: TargetingStrategy,
+    amount: usize,
+    origin: Vec2,
+    monster_index: &SpatialIndex<Monster>,
+    monster_query: &Query<(&Transform, &Health), With<Monster>>,
+    rng: &mut impl Rng,
+) -> Vec<Vec2> {
+    if strategy == TargetingStrategy::Nearest {
+        return monster_index
+            .nearest(origin, amount)
+            .into_iter()
+            .map(|(_, position)| position)
+            .collect();
+    }
+
+    let mut monsters = monster_query
+        .iter()
+        .map(|(transform, health)| (transform.translation.truncate(), health.0))
+        .collect::<Vec<_>>();
+
+    match strategy {
+        TargetingStrategy::Random => {
+            monsters.shuffle(rng);
+        }
+        TargetingStrategy::LowestHealth => {
+            monsters.sort_by_key(|(_, health)| OrderedFloat(*health));
+        }
+        TargetingStrategy::HighestHealth => {
+            monsters.sort_by_key(|(_, health)| Reverse(OrderedFloat(*health)));
+        }
+        TargetingStrategy::Furthest => {
+            monsters.sort_by_key(|(position, _)| {
+                Reverse(OrderedFloat(position.distance_squared(origin)))
+            });
+        }
+        TargetingStrategy::Nearest | TargetingStrategy::Facing => {
+            unreachable!("handled without a full scan")
+        }
+    }
+
+    monsters
+        .into_iter()
+        .take(amount)
+        .map(|(position, _)| position)
+        .collect()
+}
+
+fn spawn_projectile(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    weapon: &Weapon,
+    origin: Vec3,
+    direction: Vec3,
+) {
+    let size = PROJECTILE_SIZE * weapon.area;
+    let spawn_translation = origin + direction * MUZZLE_OFFSET;
+
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                translation: spawn_translation,
+                scale: Vec2::splat(size).extend(0.0),
+                ..Default::default()
+            },
+            sprite: Sprite {
+                color: weapon.kind.color(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::KinematicPositionBased.into(),
+            ..RigidBodyBundle::default()
+        })
+        .insert_bundle(ColliderBundle {
+            collider_type: ColliderType::Sensor.into(),
+            position: (Vector2::new(spawn_translation.x, spawn_translation.y)
+                / rapier_config.scale)
+                .into(),
+            shape: ColliderShape::cuboid(
+                size / rapier_config.scale / 2.0,
+                size / rapier_config.scale / 2.0,
+            )
+            .into(),
+            flags: ColliderFlags {
+                // Kinematic bodies ignore static ones by default, which would let projectiles
+                // fly through obstacles unnoticed
+                active_collision_types: ActiveCollisionTypes::default()
+                    | ActiveCollisionTypes::KINEMATIC_STATIC,
+                active_events: ActiveEvents::INTERSECTION_EVENTS,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(ColliderPositionSync::Discrete)
+        .insert(RunEntity)
+        .insert(Projectile {
+            weapon: weapon.kind,
+            direction,
+            speed: weapon.projectile_speed,
+            damage: weapon.damage,
+            lives: weapon.pierce,
+            hits: Vec::new(),
+            blast_radius: weapon.blast_radius,
+            range: weapon.range,
+            travelled: 0.0,
+            lifetime: Timer::from_seconds(5.0, false),
+        });
+}
@@ -0,0 +1,250 @@
+use bevy::{
+    app::Plugin,
+    math::{Vec2, Vec3},
+    prelude::{
+        App, Color, Commands, DespawnRecursiveExt, Entity, ParallelSystemDescriptorCoercion, Query,
+        Res, ResMut, SystemSet, Transform, With,
+    },
+    sprite::{Sprite, SpriteBundle},
+    utils::HashMap,
+};
+use bevy_rapier2d::{
+    na::Vector2,
+    physics::{ColliderBundle, RapierConfiguration, RigidBodyBundle},
+    prelude::{CoefficientCombineRule, ColliderMaterial, ColliderShape, RigidBodyType},
+};
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    input::TickInput,
+    rng::{stream, RunRng, WORLD_STREAM},
+    spawner::spawn_radius,
+    AddGameplaySystem, AppState, Obstacle, Player, RunEntity, RunSystem, TickPhase,
+};
+
+/// Side length of a square chunk of the world
+const CHUNK_SIZE: f32 = 512.0;
+
+/// Distance beyond the monster spawn ring kept loaded, covering the monsters spawning on it and
+/// obstacles reaching over from chunks further out
+const LOAD_MARGIN: f32 = 128.0;
+
+/// Area around the player's starting point kept free of obstacles
+const SPAWN_CLEARING: f32 = 120.0;
+
+/// The 2D camera sees down to z = -0.1, so the ground sits just above that below everything else
+const GROUND_Z: f32 = -0.09;
+const DECORATION_Z: f32 = -0.08;
+
+const OBSTACLE_COLOR: Color = Color::rgb(0.5, 0.2, 0.2);
+
+/// Chunks of the world currently spawned around the player. Every chunk is generated from the
+/// world seed and its coordinates alone, so a chunk that is unloaded and loaded again comes back
+/// the same.
+#[derive(Default)]
+pub struct WorldChunks {
+    pub seed: u64,
+    loaded: HashMap<(i32, i32), Vec<Entity>>,
+}
+
+impl WorldChunks {
+    /// Forgets every loaded chunk and starts over with a new seed. The chunks' entities are
+    /// expected to be despawned along with the rest of the run.
+    pub fn reset(&mut self, seed: u64) {
+        self.seed = seed;
+        self.loaded.clear();
+    }
+
+    fn chunk_rng(&self, chunk: (i32, i32)) -> ChaCha8Rng {
+        let x = (chunk.0 as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        let y = (chunk.1 as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+        stream(self.seed ^ x ^ y.rotate_left(32), WORLD_STREAM)
+    }
+}
+
+/// The world the run is played in, generated chunk by chunk around the player
+pub struct WorldPlugin;
+
+impl Plugin for WorldPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WorldChunks>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing)
+                    .with_system(reset_chunks.after(RunSystem::Start)),
+            )
+            .add_gameplay_system(TickPhase::World, stream_chunks);
+    }
+}
+
+fn chunk_of(position: Vec2) -> (i32, i32) {
+    let chunk = (position / CHUNK_SIZE).floor();
+    (chunk.x as i32, chunk.y as i32)
+}
+
+/// Starts the world over from the new run's seed
+fn reset_chunks(run_rng: Res<RunRng>, mut chunks: ResMut<WorldChunks>) {
+    chunks.reset(run_rng.seed());
+}
+
+/// Chunks this many chunks away from the player's, or closer, are kept loaded. Enough to cover the
+/// monster spawn ring, which lies beyond the corners of the view.
+fn load_distance(tick_input: &TickInput) -> i32 {
+    ((spawn_radius(tick_input) + LOAD_MARGIN) / CHUNK_SIZE).ceil() as i32
+}
+
+fn stream_chunks(
+    mut commands: Commands,
+    rapier_config: Res<RapierConfiguration>,
+    tick_input: Res<TickInput>,
+    mut chunks: ResMut<WorldChunks>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    let load_distance = load_distance(&tick_input);
+    // One chunk further, so walking back and forth over a chunk border doesn't regenerate chunks
+    let unload_distance = load_distance + 1;
+
+    let center = chunk_of(player_query.single().translation.truncate());
+    let distance = |chunk: (i32, i32)| (chunk.0 - center.0).abs().max((chunk.1 - center.1).abs());
+
+    let mut far_chunks = chunks
+        .loaded
+        .keys()
+        .copied()
+        .filter(|chunk| distance(*chunk) > unload_distance)
+        .collect::<Vec<_>>();
+    // Despawn in a fixed order, the physics world ends up differently depending on it
+    far_chunks.sort_unstable();
+    for chunk in far_chunks {
+        // Removing the obstacles also removes their colliders from the physics world
+        for entity in chunks.loaded.remove(&chunk).unwrap() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    for x in center.0 - load_distance..=center.0 + load_distance {
+        for y in center.1 - load_distance..=center.1 + load_distance {
+            if !chunks.loaded.contains_key(&(x, y)) {
+                let entities = spawn_chunk(&mut commands, &rapier_config, &chunks, (x, y));
+                chunks.loaded.insert((x, y), entities);
+            }
+        }
+    }
+}
+
+fn spawn_chunk(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    chunks: &WorldChunks,
+    chunk: (i32, i32),
+) -> Vec<Entity> {
+    let mut rng = chunks.chunk_rng(chunk);
+    let origin = Vec2::new(chunk.0 as f32, chunk.1 as f32) * CHUNK_SIZE;
+    let mut entities = Vec::new();
+
+    // Alternate the ground's shade like a checkerboard, so movement is visible on empty ground
+    let shade = if (chunk.0 + chunk.1).rem_euclid(2) == 0 {
+        0.12
+    } else {
+        0.14
+    } + rng.gen_range(-0.01..0.01);
+    entities.push(spawn_ground(
+        commands,
+        origin + Vec2::splat(CHUNK_SIZE / 2.0),
+        Vec2::splat(CHUNK_SIZE),
+        GROUND_Z,
+        Color::rgb(shade, shade * 1.1, shade),
+    ));
+
+    for _ in 0..rng.gen_range(4..10) {
+        let position = origin + Vec2::new(rng.gen(), rng.gen()) * CHUNK_SIZE;
+        let size = Vec2::new(rng.gen_range(10.0..40.0), rng.gen_range(10.0..40.0));
+        let shade = shade + rng.gen_range(0.02..0.06);
+        entities.push(spawn_ground(
+            commands,
+            position,
+            size,
+            DECORATION_Z,
+            Color::rgb(shade, shade * 1.2, shade),
+        ));
+    }
+
+    for _ in 0..rng.gen_range(0..5) {
+        let size = Vec2::new(rng.gen_range(30.0..120.0), rng.gen_range(30.0..120.0));
+        let position = origin + Vec2::new(rng.gen(), rng.gen()) * CHUNK_SIZE;
+
+        let reach = size / 2.0 + Vec2::splat(SPAWN_CLEARING);
+        if position.x.abs() < reach.x && position.y.abs() < reach.y {
+            continue;
+        }
+
+        entities.push(spawn_obstacle(commands, rapier_config, position, size));
+    }
+
+    entities
+}
+
+fn spawn_ground(
+    commands: &mut Commands,
+    position: Vec2,
+    size: Vec2,
+    z: f32,
+    color: Color,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform::from_translation(position.extend(z)),
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert(RunEntity)
+        .id()
+}
+
+fn spawn_obstacle(
+    commands: &mut Commands,
+    rapier_config: &RapierConfiguration,
+    position: Vec2,
+    size: Vec2,
+) -> Entity {
+    commands
+        .spawn_bundle(SpriteBundle {
+            transform: Transform {
+                translation: Vec3::new(position.x, position.y, 0.0),
+                scale: size.extend(0.0),
+                ..Default::default()
+            },
+            sprite: Sprite {
+                color: OBSTACLE_COLOR,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .insert_bundle(RigidBodyBundle {
+            body_type: RigidBodyType::Static.into(),
+            ..RigidBodyBundle::default()
+        })
+        .insert_bundle(ColliderBundle {
+            position: (Vector2::new(position.x, position.y) / rapier_config.scale).into(),
+            shape: ColliderShape::cuboid(
+                size.x / rapier_config.scale / 2.0,
+                size.y / rapier_config.scale / 2.0,
+            )
+            .into(),
+            material: ColliderMaterial {
+                friction: 0.0,
+                friction_combine_rule: CoefficientCombineRule::Min,
+                restitution: 0.0,
+                ..Default::default()
+            }
+            .into(),
+            ..Default::default()
+        })
+        .insert(Obstacle)
+        .insert(RunEntity)
+        .id()
+}